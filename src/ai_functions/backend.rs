@@ -9,6 +9,7 @@ pub fn print_backend_webserver_code(_project_description_and_template: &str) {
     /// IMPORTANT: The following libraries are already installed
     ///   reqwest, serde, serde_json, tokio, actix-web, async-trait, actix_cors
     /// No other external libraries should be used. Write functions that fit with the description from the PROJECT_DESCRIPTION
+    /// IMPORTANT: The server binds to 127.0.0.1 on the port in the PORT environment variable, or 8080 when it is not set, as in the CODE_TEMPLATE
     /// OUTPUT: Print ONLY the code, nothing else. This function ONLY prints code.
     println!(OUTPUT)
 }
@@ -22,6 +23,7 @@ pub fn print_improved_webserver_code(_project_description_and_template: &str) {
     ///   3. ONLY writes the code. No commentary.
     /// IMPORTANT: The following libraries are already installed. Does not use ANY libraries other than what was provided in the template
     ///   reqwest, serde, serde_json, tokio, actix-web, async-trait
+    /// IMPORTANT: The server binds to 127.0.0.1 on the port in the PORT environment variable, or 8080 when it is not set
     println!(OUTPUT)
 }
 
@@ -34,18 +36,17 @@ pub fn print_fixed_code(_broken_code_with_bugs: &str) {
 }
 
 #[ai_function]
-pub fn print_rest_api_endpoints(_code_input: &str) {
-    /// INPUT: Takes in Rust webserver CODE_INPUT based on actix-web
-    /// FUNCTION: Prints out the JSON schema for url endpoints and their respective types
-    /// LOGIC: Script analyses all code and can categorize into the following object keys:
-    ///   "route": This represents the url path of the endpoint
-    ///   "is_route_dynamic": if a route has curly braces in it such as {symbol} or {id} as an example, then this will be set to true
-    ///   "method": This represents the method being called
-    ///   "request_body": This represents the body of a post method request
+pub fn print_rest_api_endpoint_bodies(_code_input_and_routes: &str) {
+    /// INPUT: Takes in Rust webserver CODE_INPUT based on actix-web and the ROUTES registered in it
+    /// FUNCTION: Prints out example JSON bodies for every route in ROUTES
+    /// LOGIC: Reads the handler of each route and the structs it uses, then prints an object with the following keys:
+    ///   "route": The route exactly as given in ROUTES
+    ///   "method": The method exactly as given in ROUTES
+    ///   "request_body": This represents the body the handler expects, or "None" if it takes no body
     ///   "response": This represents the output based upon the structs in the code and understanding the functions
-    /// IMPORTANT: Only prints out the JSON schema. No commentary or anything else.
-    /// MUST READ: All keys are strings. Even bool should be wrapped in double quotes as "bool"
-    /// MUST READ: The values in "is_route_dynamic" fields are NOT TO BE WRAPPED in double quotes. They should only be `true` or `false`, as proper JSON boolean values
+    /// IMPORTANT: Only prints out the JSON array. No commentary or anything else.
+    /// IMPORTANT: Prints one object for every route in ROUTES. Never adds, removes or renames a route.
+    /// MUST READ: All body values are strings. Even bool should be wrapped in double quotes as "bool"
     /// EXAMPLE:
     /// INPUT_CODE:
     /// ...
@@ -54,37 +55,16 @@ pub fn print_rest_api_endpoints(_code_input: &str) {
     ///   pub name: String,
     ///   pub completed: bool,
     /// }
-    /// pub struct User {
-    ///   pub id: u64,
-    ///   pub username: String,
-    ///   pub password: String,
-    /// }
     /// ...
-    /// HttpServer::new(move || {
-    ///   App::new()
-    ///       .app_data(data.clone())
-    ///       .route("/item", web::post().to(create_item))
-    ///       .route("/item/{id}", web::get().to(read_item))
-    ///       .route("/item/{id}", web::put().to(update_item))
-    ///       .route("/item/{id}", web::delete().to(delete_item))
-    ///       .route("/signup", web::post().to(signup))
-    ///       .route("/crypto", web::get().to(crypto))
+    /// async fn create_item(item: web::Json<Item>) -> impl Responder { ... HttpResponse::Ok().finish() }
+    /// async fn read_item(id: web::Path<u64>) -> impl Responder { ... HttpResponse::Ok().json(item) }
+    /// ...
+    /// ROUTES:
+    /// [{"route": "/item", "method": "post"}, {"route": "/item/{id}", "method": "get"}]
     /// PRINTS JSON FORMATTED OUTPUT:
     /// [
     ///   {
-    ///     "route": "/item/{id}",
-    ///     "is_route_dynamic": "true",
-    ///     "method": "get"
-    ///     "request_body": "None",
-    ///     "response": {
-    ///       "id": "number",
-    ///       "name": "string",
-    ///       "completed": "bool",
-    ///     }
-    ///   },
-    ///   {
     ///     "route": "/item",
-    ///     "is_route_dynamic": "false",
     ///     "method": "post",
     ///     "request_body": {
     ///       "id": "number",
@@ -95,19 +75,14 @@ pub fn print_rest_api_endpoints(_code_input: &str) {
     ///   },
     ///   {
     ///     "route": "/item/{id}",
-    ///     "is_route_dynamic": "true",
-    ///     "method": "delete",
-    ///     "request_body": "None",
-    ///     "response": "None"
-    ///   },
-    ///   {
-    ///     "route": "/crypto",
-    ///     "is_route_dynamic": "false",
     ///     "method": "get",
     ///     "request_body": "None",
-    ///     "response": "not_provided"
-    ///   },
-    ///   ... // etc
+    ///     "response": {
+    ///       "id": "number",
+    ///       "name": "string",
+    ///       "completed": "bool",
+    ///     }
+    ///   }
     /// ]
     println!(OUTPUT)
 }
@@ -175,17 +175,15 @@ pub fn print_header_navigation_react_component(_website_specification: &str) {
   /// FUNCTION: Writes the code for a REACT TYPESCRIPT navigation header bar for a frontend website
   /// OUTPOUT: The navigation header bar includes the following
   ///   1 - The existing logo which shows as <Logo />, the logo should have an 'import Logo from "./Logo"' at the top of the component
-  ///   2 - Page links based on the pages provided in the specification
+  ///   2 - One link for every entry of navLinks from the NAV_LINKS_MODULE provided in the specification. Links are never hardcoded
   ///   3 - This should be responsive based upon the size of the screen using tailwind css. A small screen should have a burger menu with slider
-  ///   4 - Takes in a getter and setter prop called currentPage and setCurrentPage respectively
-  ///   5 - Depending on the page, a different color will show on the navigation links
+  ///   4 - Every link is a NavLink from react-router-dom using the path of its navLinks entry. The component takes no props
+  ///   5 - Depending on the page, a different color will show on the navigation links (use the isActive value NavLink provides)
   /// TEMPLATE:
+  /// import { NavLink } from "react-router-dom"
+  /// import { navLinks } from "../../navLinks"
   /// import Logo from "./Logo"
-  /// type Props = {
-  ///   currentPage: string;
-  ///   setCurrentPage: any; // Leave this as any as an unknown setter function will be passed here
-  /// }
-  /// function Navigation({setCurrentPage, currentPage}: Props) {
+  /// function Navigation() {
   ///   return (
   ///     <div>
   ///       YOUR COMPONENT CODE GOES HERE
@@ -195,7 +193,7 @@ pub fn print_header_navigation_react_component(_website_specification: &str) {
   /// export default Navigation
   /// DO NOT LEAVE ANY CODE UNFINISHED FOR LATER. CODE EVERYTHING INCLUDING THE SLIDER MENU NOW.
   /// IMPORTANT: This function only prints a full react component with completed typescript code, nothing else. Just the code and WITHOUT any backticks at the start of the file ```.
-  /// IMPORTANT: Tailwind CSS is used for styling. Does NOT use any extrernal libraries not included in this list: [axios, @mui/icons-material", react, tailwind]
+  /// IMPORTANT: Tailwind CSS is used for styling. Does NOT use any extrernal libraries not included in this list: [axios, @mui/icons-material", react, react-router-dom, tailwind]
   println!(OUTPUT)
 }
 
@@ -206,16 +204,14 @@ pub fn print_footer_navigation_react_component(_website_specification: &str) {
   /// INPUT: Takes in a WEBSITE_SPECIFICATION for a websites frontend.
   /// FUNCTION: Writes only the HTML code for a REACT TYPESCRIPT footer for website
   /// OUTPOUT: The navigation footer bar includes the following
-  ///   1 - Page links based on the pages provided in the specification
+  ///   1 - One link for every entry of navLinks from the NAV_LINKS_MODULE provided in the specification. Links are never hardcoded
   ///   2 - Must be responsive and be a small fixed bar to the bottom of the screen if in mobile view
-  ///   3 - Takes in a getter and setter prop called currentPage and setCurrentPage respectively
-  ///   4 - Depending on the page, a different color will show on the navigation links
+  ///   3 - Every link is a NavLink from react-router-dom using the path of its navLinks entry. The component takes no props
+  ///   4 - Depending on the page, a different color will show on the navigation links (use the isActive value NavLink provides)
   /// TEMPLATE:
-  /// type Props = {
-  ///   currentPage: string;
-  ///   setCurrentPage: any; // Leave this as any as an unknown setter function will be passed here
-  /// }
-  /// function Footer({setCurrentPage, currentPage}: Props) {
+  /// import { NavLink } from "react-router-dom"
+  /// import { navLinks } from "../../navLinks"
+  /// function Footer() {
   ///   return (
   ///     <div>
   ///       YOUR COMPONENT CODE GOES HERE
@@ -224,7 +220,7 @@ pub fn print_footer_navigation_react_component(_website_specification: &str) {
   /// }
   /// export default Footer
   /// IMPORTANT: This function only prints a full react component with completed typescript code, nothing else. Just the code and WITHOUT any backticks at the start of the file ```.
-  /// IMPORTANT: Tailwind CSS is used for styling. Does NOT use any extrernal libraries not included in this list: [axios, @mui/icons-material", react, tailwind]
+  /// IMPORTANT: Tailwind CSS is used for styling. Does NOT use any extrernal libraries not included in this list: [axios, @mui/icons-material", react, react-router-dom, tailwind]
   println!(OUTPUT)
 }
 
@@ -268,7 +264,7 @@ pub fn print_html_webpage_content_with_text(_page_content_spec: &str) {
 
 // Integration - Create Component Template - API Integration
 #[ai_function]
-pub fn print_create_react_component_with_API_integration(_page_specification: &str) {
+pub fn print_create_react_component_with_api_integration(_page_specification: &str) {
   /// INPUT: Receives API_SPECIFICATION information with API_ROUTES and API_HOOK relevant to page if any
   /// OUTPUT: Converts the input into a full REACT TYPESCRIPT based component including handling the required API requests
   /// and presenting the data in the component render section.
@@ -1,74 +1,246 @@
-use crate::models::general::llm::{APIResponse, ChatCompletion, Message};
-use dotenv::dotenv;
-use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::Client;
+use crate::models::general::{
+    errors::AgentError,
+    llm::{APIResponse, APIStreamChunk, ChatCompletion, LlmResponse, StreamOptions, TokenUsage},
+};
+use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use reqwest::{Client, Response};
 use std::env;
+use std::future::Future;
+use std::time::Duration;
 
-// Call Large Language Model (i.e. GPT-4)
-pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::error::Error + Send>> {
-    dotenv().ok();
+// Marks the end of a server-sent events stream of completion chunks
+const STREAM_DONE: &str = "[DONE]";
 
-    let (api_key, api_org) = extract_keys();
-    let url: &str = "https://api.openai.com/v1/chat/completions";
-    let headers: HeaderMap = create_headers(&api_key, &api_org)?;
+tokio::task_local! {
+    // Longest wait for the next piece of a response, set by the retry policy, see with_idle_timeout
+    static IDLE_TIMEOUT: Duration;
+}
 
-    let client: Client = Client::builder()
-        .default_headers(headers)
-        .build()
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
+// Runs `future` with every wait on the LLM server (for the response, then for each streamed chunk)
+// limited to `timeout`, so a long completion that keeps streaming is never cut off
+pub fn with_idle_timeout<F: Future>(
+    timeout: Duration,
+    future: F,
+) -> impl Future<Output = F::Output> {
+    IDLE_TIMEOUT.scope(timeout, future)
+}
 
-    let chat_completion: ChatCompletion = ChatCompletion {
-        model: "gpt-4".to_string(),
-        messages,
-        temperature: 0.1, // docs: https://platform.openai.com/docs/guides/text-generation/how-should-i-set-the-temperature-parameter
+// Calls made outside with_idle_timeout wait as long as the server takes
+async fn within_idle_timeout<T, F>(future: F) -> Result<T, AgentError>
+where
+    F: Future<Output = Result<T, reqwest::Error>>,
+{
+    let timeout: Duration = match IDLE_TIMEOUT.try_with(|timeout| *timeout) {
+        Ok(timeout) => timeout,
+        Err(_) => return Ok(future.await?),
     };
+    match tokio::time::timeout(timeout, future).await {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(AgentError::Transport(format!(
+            "LLM server sent nothing for {:?}",
+            timeout
+        ))),
+    }
+}
+
+// Call Large Language Model (i.e. GPT-4) on any OpenAI compatible chat completions endpoint
+pub async fn call_gpt(
+    url: &str,
+    headers: HeaderMap,
+    chat_completion: &ChatCompletion,
+) -> Result<LlmResponse, AgentError> {
+    let client: Client = Client::builder().default_headers(headers).build()?;
+
+    // Not streamed: the server only answers once the whole completion is written
+    let response: Response =
+        within_idle_timeout(client.post(url).json(chat_completion).send()).await?;
+    let response: Response = error_for_status(response).await?;
 
     // Extract API Response
-    let response: APIResponse = client
-        .post(url)
-        .json(&chat_completion)
-        .send()
-        .await
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?
-        .json()
-        .await
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
+    let response: APIResponse = within_idle_timeout(response.json()).await?;
+
+    match response.choices.first() {
+        Some(choice) => Ok(LlmResponse {
+            content: choice.message.content.to_string(),
+            model: response.model.or(Some(chat_completion.model.clone())),
+            usage: response.usage,
+        }),
+        None => Err(AgentError::Transport(format!(
+            "{} returned no completion choices",
+            url
+        ))),
+    }
+}
+
+// Same as call_gpt, but the completion is streamed as server-sent events and every
+// piece of text is handed to `on_token` as soon as it arrives
+// docs: https://platform.openai.com/docs/api-reference/chat/streaming
+pub async fn call_gpt_stream<F: FnMut(&str)>(
+    url: &str,
+    headers: HeaderMap,
+    chat_completion: &ChatCompletion,
+    mut on_token: F,
+) -> Result<LlmResponse, AgentError> {
+    let client: Client = Client::builder().default_headers(headers).build()?;
+
+    let mut streamed_completion: ChatCompletion = chat_completion.clone();
+    streamed_completion.stream = true;
+    streamed_completion.stream_options = Some(StreamOptions {
+        include_usage: true,
+    });
+
+    let response: Response =
+        within_idle_timeout(client.post(url).json(&streamed_completion).send()).await?;
+    let mut response: Response = error_for_status(response).await?;
 
-    return Ok(response.choices[0].message.content.to_string());
+    let mut stream: StreamAssembler = StreamAssembler::default();
+    while let Some(bytes) = within_idle_timeout(response.chunk()).await? {
+        stream.feed(&bytes, &mut on_token)?;
+        if stream.done {
+            break;
+        }
+    }
+    stream.finish(url, &chat_completion.model)
 }
 
-fn extract_keys() -> (String, String) {
-    let api_key: String =
-        env::var("OPEN_AI_KEY").expect("OPEN_AI_KEY missing in environment variables");
-    let api_org: String =
-        env::var("OPEN_AI_ORG").expect("OPEN_AI_ORG missing in environment variables");
-    return (api_key, api_org);
+// Keep the status and Retry-After header so the retry policy can tell rate limits from bad keys
+async fn error_for_status(response: Response) -> Result<Response, AgentError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after: Option<Duration> = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let message: String = response.text().await.unwrap_or_default();
+
+    Err(AgentError::Http {
+        status: status.as_u16(),
+        retry_after,
+        message,
+    })
 }
 
-fn create_headers(
-    api_key: &str,
-    api_org: &str,
-) -> Result<HeaderMap, Box<dyn std::error::Error + Send>> {
+// Builds the full completion back up from `data:` lines, which may be split across network chunks
+#[derive(Debug, Default)]
+pub struct StreamAssembler {
+    pending: Vec<u8>,
+    content: String,
+    model: Option<String>,
+    usage: Option<TokenUsage>,
+    has_choices: bool,
+    pub done: bool,
+}
+
+impl StreamAssembler {
+    pub fn feed<F: FnMut(&str)>(
+        &mut self,
+        bytes: &[u8],
+        on_token: &mut F,
+    ) -> Result<(), AgentError> {
+        self.pending.extend_from_slice(bytes);
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.read_line(String::from_utf8_lossy(&line).trim(), on_token)?;
+        }
+        Ok(())
+    }
+
+    // Comments, keep-alives and other event fields carry no text
+    fn read_line<F: FnMut(&str)>(
+        &mut self,
+        line: &str,
+        on_token: &mut F,
+    ) -> Result<(), AgentError> {
+        let data: &str = match line.strip_prefix("data:") {
+            Some(data) => data.trim(),
+            None => return Ok(()),
+        };
+        if data == STREAM_DONE {
+            self.done = true;
+            return Ok(());
+        }
+
+        let chunk: APIStreamChunk = serde_json::from_str(data)?;
+        if let Some(error) = chunk.error {
+            return Err(AgentError::Transport(format!(
+                "Completion stream failed: {}",
+                error
+            )));
+        }
+        self.model = chunk.model.or(self.model.take());
+        self.usage = chunk.usage.or(self.usage);
+        for choice in chunk.choices {
+            self.has_choices = true;
+            if let Some(token) = choice.delta.content.filter(|token| !token.is_empty()) {
+                on_token(&token);
+                self.content.push_str(&token);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self, url: &str, model: &str) -> Result<LlmResponse, AgentError> {
+        // A last line without a trailing newline
+        if !self.pending.is_empty() {
+            let line: String = String::from_utf8_lossy(&self.pending).trim().to_string();
+            self.pending.clear();
+            self.read_line(&line, &mut |_: &str| {})?;
+        }
+        if !self.has_choices {
+            return Err(AgentError::Transport(format!(
+                "{} returned no completion choices",
+                url
+            )));
+        }
+        Ok(LlmResponse {
+            content: self.content,
+            model: self.model.or(Some(model.to_string())),
+            usage: self.usage,
+        })
+    }
+}
+
+pub fn extract_keys() -> Result<(String, String), AgentError> {
+    let api_key: String = env::var("OPEN_AI_KEY").map_err(|_| {
+        AgentError::Config("OPEN_AI_KEY missing in environment variables".to_string())
+    })?;
+    let api_org: String = env::var("OPEN_AI_ORG").map_err(|_| {
+        AgentError::Config("OPEN_AI_ORG missing in environment variables".to_string())
+    })?;
+    Ok((api_key, api_org))
+}
+
+pub fn create_headers(api_key: &str, api_org: Option<&str>) -> Result<HeaderMap, AgentError> {
     let mut headers: HeaderMap = HeaderMap::new();
 
     headers.insert(
         "authorization",
         HeaderValue::from_str(&format!("Bearer {}", api_key))
-            .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?,
+            .map_err(|e| AgentError::Config(format!("Invalid API key header: {}", e)))?,
     );
 
-    headers.insert(
-        "OpenAI-Organization",
-        HeaderValue::from_str(api_org.to_string().as_str())
-            .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?,
-    );
+    if let Some(api_org) = api_org {
+        headers.insert(
+            "OpenAI-Organization",
+            HeaderValue::from_str(api_org)
+                .map_err(|e| AgentError::Config(format!("Invalid API org header: {}", e)))?,
+        );
+    }
 
-    return Ok(headers);
+    Ok(headers)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::apis::replay_provider::test_provider;
+    use crate::models::general::llm::{Message, TokenUsage};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
     #[tokio::test]
     async fn test_call_to_openai() {
@@ -77,6 +249,148 @@ mod tests {
             content: "Hi there, this is a test. Give me a short response.".to_string(),
         };
 
-        call_gpt(vec![message]).await;
+        let res: Result<LlmResponse, AgentError> = test_provider().call(vec![message]).await;
+
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn tests_api_response_usage_is_optional() {
+        let with_usage: APIResponse = serde_json::from_str(
+            "{\"model\":\"gpt-4-0613\",\"choices\":[{\"message\":{\"content\":\"hi\"}}],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":3,\"total_tokens\":15}}",
+        )
+        .unwrap();
+        assert_eq!(
+            with_usage.usage,
+            Some(TokenUsage {
+                prompt_tokens: 12,
+                completion_tokens: 3
+            })
+        );
+
+        let without_usage: APIResponse =
+            serde_json::from_str("{\"choices\":[{\"message\":{\"content\":\"hi\"}}]}").unwrap();
+        assert!(without_usage.usage.is_none());
+    }
+
+    // Streams a completion as server-sent events, one line every `interval`
+    async fn slow_stream_server(lines: Vec<&'static str>, interval: Duration) -> String {
+        let listener: tokio::net::TcpListener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let url: String = format!(
+            "http://{}/v1/chat/completions",
+            listener.local_addr().unwrap()
+        );
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request: Vec<u8> = vec![0; 8192];
+            let _ = socket.read(&mut request).await;
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            for line in lines {
+                tokio::time::sleep(interval).await;
+                if socket
+                    .write_all(format!("{}\n", line).as_bytes())
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn tests_idle_timeout_applies_between_chunks() {
+        let chat_completion: ChatCompletion = ChatCompletion {
+            model: "llama3".to_string(),
+            messages: vec![],
+            temperature: 0.1,
+            stream: false,
+            stream_options: None,
+        };
+        let lines: Vec<&'static str> = vec![
+            "data: {\"choices\":[{\"delta\":{\"content\":\"fn \"}}]}",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"main\"}}]}",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"() {}\"}}]}",
+            "data: [DONE]",
+        ];
+
+        // Takes longer than the timeout in total, but never waits that long for a chunk
+        let url: String = slow_stream_server(lines.clone(), Duration::from_millis(150)).await;
+        let response: LlmResponse = with_idle_timeout(
+            Duration::from_millis(400),
+            call_gpt_stream(&url, HeaderMap::new(), &chat_completion, |_: &str| {}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.content, "fn main() {}");
+
+        let url: String = slow_stream_server(lines, Duration::from_millis(600)).await;
+        let stalled: AgentError = with_idle_timeout(
+            Duration::from_millis(200),
+            call_gpt_stream(&url, HeaderMap::new(), &chat_completion, |_: &str| {}),
+        )
+        .await
+        .unwrap_err();
+        assert!(stalled.to_string().contains("sent nothing"));
+    }
+
+    #[test]
+    fn tests_create_headers_without_org() {
+        let headers: HeaderMap = create_headers("dummy key", None).unwrap();
+        assert_eq!(headers.get("authorization").unwrap(), "Bearer dummy key");
+        assert!(headers.get("OpenAI-Organization").is_none());
+    }
+
+    #[test]
+    fn tests_stream_chunks_are_assembled() {
+        let body: String = [
+            ": keep-alive",
+            "data: {\"model\":\"gpt-4o-2024\",\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":\"\"}}]}",
+            "data: {\"model\":\"gpt-4o-2024\",\"choices\":[{\"delta\":{\"content\":\"fn main() \"}}]}",
+            "data: {\"model\":\"gpt-4o-2024\",\"choices\":[{\"delta\":{\"content\":\"{ println!(\\\"héllo\\\"); }\"}}]}",
+            "data: {\"model\":\"gpt-4o-2024\",\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":4}}",
+            "data: [DONE]",
+            "",
+        ]
+        .join("\n");
+
+        // Network chunks split lines and even characters in two
+        let mut tokens: Vec<String> = vec![];
+        let mut stream: StreamAssembler = StreamAssembler::default();
+        for bytes in body.as_bytes().chunks(7) {
+            stream
+                .feed(bytes, &mut |token: &str| tokens.push(token.to_string()))
+                .unwrap();
+        }
+        assert!(stream.done);
+
+        let response: LlmResponse = stream.finish("http://localhost", "gpt-4o").unwrap();
+        assert_eq!(tokens, vec!["fn main() ", "{ println!(\"héllo\"); }"]);
+        assert_eq!(response.content, "fn main() { println!(\"héllo\"); }");
+        assert_eq!(response.model, Some("gpt-4o-2024".to_string()));
+        assert_eq!(
+            response.usage,
+            Some(TokenUsage {
+                prompt_tokens: 9,
+                completion_tokens: 4
+            })
+        );
+
+        let mut failed: StreamAssembler = StreamAssembler::default();
+        assert!(failed
+            .feed(
+                b"data: {\"error\":{\"message\":\"overloaded\"}}\n",
+                &mut |_: &str| {}
+            )
+            .is_err());
+        assert!(StreamAssembler::default()
+            .finish("http://localhost", "gpt-4o")
+            .is_err());
     }
 }
@@ -0,0 +1,259 @@
+use std::env;
+
+use crate::helpers::command_line::PrintCommand;
+use crate::models::general::llm::Message;
+
+// Context window in tokens and average characters per token (code tokenizes worse than prose),
+// matched on the longest model name prefix
+// docs: https://platform.openai.com/docs/models
+const MODEL_CONTEXT: [(&str, usize, f64); 9] = [
+    ("gpt-4o-mini", 128_000, 3.8),
+    ("gpt-4o", 128_000, 3.8),
+    ("gpt-4-turbo", 128_000, 3.5),
+    ("gpt-4-32k", 32_768, 3.5),
+    ("gpt-4", 8_192, 3.5),
+    ("gpt-3.5-turbo", 16_385, 3.5),
+    ("llama3.1", 128_000, 3.5),
+    ("llama3", 8_192, 3.5),
+    ("mistral", 32_768, 3.0),
+];
+const DEFAULT_CONTEXT_WINDOW: usize = 8_192;
+const DEFAULT_CHARS_PER_TOKEN: f64 = 3.0;
+
+// docs: https://github.com/openai/openai-cookbook/blob/main/examples/How_to_count_tokens_with_tiktoken.ipynb
+const TOKENS_PER_MESSAGE: usize = 4;
+const TOKENS_PER_REPLY: usize = 3;
+
+// Room left for the answer, the generated code is often as long as the code sent
+const MAX_RESERVED_FOR_COMPLETION: usize = 4_096;
+
+// Token limits of one model, used to trim prompts before they are sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextBudget {
+    pub model: String,
+    pub context_window: usize,
+    pub chars_per_token: f64,
+}
+
+impl ContextBudget {
+    // LLM_CONTEXT_WINDOW overrides the table, i.e. for a local model served with a larger num_ctx
+    pub fn for_model(model: &str) -> Self {
+        let (context_window, chars_per_token): (usize, f64) = match MODEL_CONTEXT
+            .iter()
+            .filter(|(prefix, _, _)| model.starts_with(prefix))
+            .max_by_key(|(prefix, _, _)| prefix.len())
+        {
+            Some((_, context_window, chars_per_token)) => (*context_window, *chars_per_token),
+            None => (DEFAULT_CONTEXT_WINDOW, DEFAULT_CHARS_PER_TOKEN),
+        };
+        let context_window: usize = env::var("LLM_CONTEXT_WINDOW")
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .unwrap_or(context_window);
+
+        Self {
+            model: model.to_string(),
+            context_window,
+            chars_per_token,
+        }
+    }
+
+    pub fn max_prompt_tokens(&self) -> usize {
+        self.context_window - MAX_RESERVED_FOR_COMPLETION.min(self.context_window / 4)
+    }
+
+    // An estimate, close enough to stay under the limit without shipping the model's tokenizer
+    pub fn count_tokens(&self, text: &str) -> usize {
+        (text.chars().count() as f64 / self.chars_per_token).ceil() as usize
+    }
+
+    pub fn count_message_tokens(&self, messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|message| self.count_tokens(&message.content) + TOKENS_PER_MESSAGE)
+            .sum::<usize>()
+            + TOKENS_PER_REPLY
+    }
+
+    // Drops the oldest history turns, then cuts the middle out of the largest messages until the
+    // prompt fits. System messages (the function prompts) and the last message (the current request)
+    // are never dropped. Some(warning) when anything was trimmed
+    pub fn fit(&self, mut messages: Vec<Message>) -> (Vec<Message>, Option<String>) {
+        let max_tokens: usize = self.max_prompt_tokens();
+        let original_tokens: usize = self.count_message_tokens(&messages);
+        if original_tokens <= max_tokens {
+            return (messages, None);
+        }
+
+        let mut dropped: usize = 0;
+        while self.count_message_tokens(&messages) > max_tokens {
+            let last: usize = messages.len().saturating_sub(1);
+            let oldest_turn: usize = match messages
+                .iter()
+                .take(last)
+                .position(|message| message.role != "system")
+            {
+                Some(oldest_turn) => oldest_turn,
+                None => break,
+            };
+            messages.remove(oldest_turn);
+            dropped += 1;
+        }
+
+        let mut truncated_chars: usize = 0;
+        loop {
+            let tokens: usize = self.count_message_tokens(&messages);
+            if tokens <= max_tokens {
+                break;
+            }
+            let largest: &mut Message = match messages
+                .iter_mut()
+                .max_by_key(|message| message.content.chars().count())
+            {
+                Some(largest) => largest,
+                None => break,
+            };
+
+            let length: usize = largest.content.chars().count();
+            let excess_chars: usize =
+                ((tokens - max_tokens) as f64 * self.chars_per_token).ceil() as usize;
+            let marker: String = truncation_marker(length, &self.model);
+            let keep: usize = length.saturating_sub(excess_chars + marker.chars().count());
+            let shortened: String = truncate_middle(&largest.content, keep, &self.model);
+            // Nothing left to cut, i.e. a single message whose marker alone is over the limit
+            if shortened.chars().count() >= length {
+                break;
+            }
+            truncated_chars += length - keep;
+            largest.content = shortened;
+        }
+
+        let mut trimmed: Vec<String> = vec![];
+        if dropped > 0 {
+            trimmed.push(format!("dropped the {} oldest history messages", dropped));
+        }
+        if truncated_chars > 0 {
+            trimmed.push(format!(
+                "truncated {} characters from the largest messages",
+                truncated_chars
+            ));
+        }
+        let warning: String = format!(
+            "Prompt of ~{} tokens is over the {} token budget for {}: {} (now ~{} tokens)",
+            original_tokens,
+            max_tokens,
+            self.model,
+            trimmed.join(" and "),
+            self.count_message_tokens(&messages)
+        );
+        (messages, Some(warning))
+    }
+}
+
+// Trims the messages to the model's context window, warning when anything was cut
+pub fn fit_to_context(model: &str, messages: Vec<Message>) -> Vec<Message> {
+    let (messages, warning): (Vec<Message>, Option<String>) =
+        ContextBudget::for_model(model).fit(messages);
+    if let Some(warning) = warning {
+        PrintCommand::Issue.print_agent_message("Context Budget", warning.as_str());
+    }
+    messages
+}
+
+fn truncation_marker(removed: usize, model: &str) -> String {
+    format!(
+        "\n... [{} characters truncated to fit the {} context window] ...\n",
+        removed, model
+    )
+}
+
+// Keeps the start and end of the text, which is where code has its imports and entry point
+fn truncate_middle(text: &str, keep: usize, model: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let keep: usize = keep.min(chars.len());
+    let head: String = chars[..keep / 2].iter().collect();
+    let tail: String = chars[chars.len() - (keep - keep / 2)..].iter().collect();
+    format!(
+        "{}{}{}",
+        head,
+        truncation_marker(chars.len() - keep, model),
+        tail
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: String) -> Message {
+        Message {
+            role: role.to_string(),
+            content,
+        }
+    }
+
+    #[test]
+    fn tests_prompts_are_trimmed_to_the_context_window() {
+        let gpt_4: ContextBudget = ContextBudget {
+            model: "gpt-4".to_string(),
+            context_window: 8_192,
+            chars_per_token: 3.5,
+        };
+        assert_eq!(gpt_4.max_prompt_tokens(), 6_144);
+        assert_eq!(gpt_4.count_tokens("fn main() {}"), 4);
+
+        let small: Vec<Message> = vec![message("user", "Hi there".to_string())];
+        let (fitted, warning) = gpt_4.fit(small.clone());
+        assert_eq!(fitted, small);
+        assert!(warning.is_none());
+
+        let budget: ContextBudget = ContextBudget {
+            model: "llama3".to_string(),
+            context_window: 400,
+            chars_per_token: 4.0,
+        };
+        let code: String = format!("use actix_web::*;\n{}\nfn main() {{}}", "x".repeat(2_000));
+        let messages: Vec<Message> = vec![
+            message("assistant", "original code".repeat(20)),
+            message("assistant", "previous fix".repeat(20)),
+            message("system", code),
+        ];
+        let (fitted, warning) = budget.fit(messages.clone());
+
+        assert_eq!(fitted.len(), 1);
+        assert!(budget.count_message_tokens(&fitted) <= budget.max_prompt_tokens());
+        assert!(fitted[0].content.starts_with("use actix_web::*;"));
+        assert!(fitted[0].content.ends_with("fn main() {}"));
+        assert!(fitted[0]
+            .content
+            .contains("characters truncated to fit the llama3 context window"));
+        let warning: String = warning.unwrap();
+        assert!(warning.contains("dropped the 2 oldest history messages"));
+        assert!(warning.contains("truncated"));
+
+        // The stale original code goes before the newer turn, the function prompt stays
+        let fix_loop: Vec<Message> = vec![
+            message("assistant", "original code ".repeat(60)),
+            message("assistant", "previous fix ".repeat(40)),
+            message("system", "FUNCTION print_fixed_code ".repeat(20)),
+        ];
+        let (fitted, _) = budget.fit(fix_loop.clone());
+        assert_eq!(fitted, vec![fix_loop[1].clone(), fix_loop[2].clone()]);
+
+        // A decode repair keeps the prompt it refers to, only the bad answer goes
+        let repair: Vec<Message> = vec![
+            message("system", "FUNCTION print_code ".repeat(40)),
+            message("assistant", "not json ".repeat(200)),
+            message("user", "Print ONLY the corrected JSON".to_string()),
+        ];
+        let (fitted, _) = budget.fit(repair.clone());
+        assert_eq!(fitted, vec![repair[0].clone(), repair[2].clone()]);
+
+        // The longest matching prefix wins, so gpt-4o is not mistaken for gpt-4
+        assert_eq!(
+            ContextBudget::for_model("gpt-4o-2024-08-06").chars_per_token,
+            3.8
+        );
+        assert_eq!(ContextBudget::for_model("gpt-4-0613").chars_per_token, 3.5);
+    }
+}
@@ -0,0 +1,289 @@
+use async_trait::async_trait;
+use dotenv::dotenv;
+use reqwest::header::HeaderMap;
+use std::env;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::call_request::{call_gpt, call_gpt_stream, create_headers, extract_keys};
+use super::context_budget::fit_to_context;
+use super::replay_provider::ReplayProvider;
+use crate::helpers::command_line::PrintCommand;
+use crate::models::general::{
+    errors::AgentError,
+    llm::{ChatCompletion, LlmResponse, Message},
+};
+
+const OPEN_AI_URL: &str = "https://api.openai.com/v1/chat/completions";
+const OPEN_AI_MODEL: &str = "gpt-4";
+const LOCAL_LLM_URL: &str = "http://localhost:11434/v1/chat/completions";
+const LOCAL_LLM_MODEL: &str = "llama3";
+
+// docs: https://platform.openai.com/docs/guides/text-generation/how-should-i-set-the-temperature-parameter
+const DEFAULT_TEMPERATURE: f32 = 0.1;
+
+// Command line settings that take precedence over LLM_MODEL / LLM_TEMPERATURE
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderOverrides {
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+}
+
+impl ProviderOverrides {
+    fn model(&self, default_model: &str) -> String {
+        self.model
+            .clone()
+            .or(env::var("LLM_MODEL").ok())
+            .unwrap_or(default_model.to_string())
+    }
+
+    fn temperature(&self) -> f32 {
+        self.temperature
+            .or(env::var("LLM_TEMPERATURE")
+                .ok()
+                .and_then(|value| value.trim().parse::<f32>().ok()))
+            .unwrap_or(DEFAULT_TEMPERATURE)
+    }
+}
+
+// Any backend able to answer a chat completion request (OpenAI, self-hosted models, test doubles...)
+#[async_trait]
+pub trait LlmProvider: Debug + Send + Sync {
+    // Used for logging which backend is answering -- useful for debugging
+    fn name(&self) -> String;
+
+    // Used to estimate what a call will cost before it is made, None when nothing is charged
+    fn model(&self) -> Option<String> {
+        None
+    }
+
+    async fn call(&self, messages: Vec<Message>) -> Result<LlmResponse, AgentError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenAIProvider {
+    api_key: String,
+    api_org: String,
+    model: String,
+    temperature: f32,
+    stream: bool,
+}
+
+impl OpenAIProvider {
+    pub fn new(api_key: String, api_org: String, model: String, temperature: f32) -> Self {
+        Self {
+            api_key,
+            api_org,
+            model,
+            temperature,
+            stream: false,
+        }
+    }
+
+    // Streamed completions are echoed as they arrive, see call_gpt_stream
+    pub fn streaming(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn from_env(overrides: &ProviderOverrides) -> Result<Self, AgentError> {
+        dotenv().ok();
+
+        let (api_key, api_org) = extract_keys()?;
+        Ok(Self::new(
+            api_key,
+            api_org,
+            overrides.model(OPEN_AI_MODEL),
+            overrides.temperature(),
+        )
+        .streaming(stream_from_env()))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAIProvider {
+    fn name(&self) -> String {
+        format!("OpenAI ({})", self.model)
+    }
+
+    fn model(&self) -> Option<String> {
+        Some(self.model.clone())
+    }
+
+    async fn call(&self, messages: Vec<Message>) -> Result<LlmResponse, AgentError> {
+        let headers: HeaderMap = create_headers(&self.api_key, Some(&self.api_org))?;
+
+        let chat_completion: ChatCompletion = ChatCompletion {
+            model: self.model.clone(),
+            messages: fit_to_context(&self.model, messages),
+            temperature: self.temperature,
+            stream: false,
+            stream_options: None,
+        };
+
+        return complete(OPEN_AI_URL, headers, &chat_completion, self.stream).await;
+    }
+}
+
+// Self-hosted model served behind an OpenAI compatible API (i.e. Ollama, vLLM, llama.cpp server)
+#[derive(Debug, Clone)]
+pub struct LocalProvider {
+    url: String,
+    api_key: Option<String>,
+    model: String,
+    temperature: f32,
+    stream: bool,
+}
+
+impl LocalProvider {
+    pub fn new(url: String, api_key: Option<String>, model: String, temperature: f32) -> Self {
+        Self {
+            url,
+            api_key,
+            model,
+            temperature,
+            stream: false,
+        }
+    }
+
+    // Streamed completions are echoed as they arrive, see call_gpt_stream
+    pub fn streaming(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn from_env(overrides: &ProviderOverrides) -> Self {
+        dotenv().ok();
+
+        let url: String = env::var("LOCAL_LLM_URL").unwrap_or(LOCAL_LLM_URL.to_string());
+        let api_key: Option<String> = env::var("LOCAL_LLM_KEY").ok();
+        Self::new(
+            url,
+            api_key,
+            overrides.model(LOCAL_LLM_MODEL),
+            overrides.temperature(),
+        )
+        .streaming(stream_from_env())
+    }
+}
+
+#[async_trait]
+impl LlmProvider for LocalProvider {
+    fn name(&self) -> String {
+        format!("Local ({} @ {})", self.model, self.url)
+    }
+
+    fn model(&self) -> Option<String> {
+        Some(self.model.clone())
+    }
+
+    async fn call(&self, messages: Vec<Message>) -> Result<LlmResponse, AgentError> {
+        let headers: HeaderMap = match &self.api_key {
+            Some(api_key) => create_headers(api_key, None)?,
+            None => HeaderMap::new(),
+        };
+
+        let chat_completion: ChatCompletion = ChatCompletion {
+            model: self.model.clone(),
+            messages: fit_to_context(&self.model, messages),
+            temperature: self.temperature,
+            stream: false,
+            stream_options: None,
+        };
+
+        return complete(&self.url, headers, &chat_completion, self.stream).await;
+    }
+}
+
+// Streaming is on unless LLM_STREAM=false, i.e. for servers without server-sent events support
+fn stream_from_env() -> bool {
+    env::var("LLM_STREAM")
+        .map(|value| !matches!(value.trim().to_lowercase().as_str(), "false" | "0" | "off"))
+        .unwrap_or(true)
+}
+
+async fn complete(
+    url: &str,
+    headers: HeaderMap,
+    chat_completion: &ChatCompletion,
+    stream: bool,
+) -> Result<LlmResponse, AgentError> {
+    if !stream {
+        return call_gpt(url, headers, chat_completion).await;
+    }
+
+    let response: Result<LlmResponse, AgentError> =
+        call_gpt_stream(url, headers, chat_completion, |token: &str| {
+            PrintCommand::Stream.print_stream_token(token)
+        })
+        .await;
+    PrintCommand::Stream.end_stream();
+    response
+}
+
+// Selects the provider from the environment, recording or replaying fixtures when LLM_FIXTURES_DIR is set
+pub fn provider_from_env(
+    overrides: &ProviderOverrides,
+) -> Result<Arc<dyn LlmProvider>, AgentError> {
+    dotenv().ok();
+
+    let fixtures_dir: Option<PathBuf> = env::var("LLM_FIXTURES_DIR").ok().map(PathBuf::from);
+    let fixtures_mode: String = env::var("LLM_FIXTURES_MODE").unwrap_or("replay".to_string());
+
+    match fixtures_dir {
+        Some(dir) if fixtures_mode == "record" => Ok(Arc::new(ReplayProvider::record(
+            dir,
+            live_provider_from_env(overrides)?,
+        ))),
+        Some(dir) => Ok(Arc::new(ReplayProvider::replay(dir))),
+        None => live_provider_from_env(overrides),
+    }
+}
+
+// Selects the provider from the LLM_PROVIDER environment variable, defaulting to OpenAI
+pub fn live_provider_from_env(
+    overrides: &ProviderOverrides,
+) -> Result<Arc<dyn LlmProvider>, AgentError> {
+    dotenv().ok();
+
+    let provider: String = env::var("LLM_PROVIDER").unwrap_or("openai".to_string());
+    match provider.to_lowercase().as_str() {
+        "local" => Ok(Arc::new(LocalProvider::from_env(overrides))),
+        _ => Ok(Arc::new(OpenAIProvider::from_env(overrides)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tests_local_provider_unreachable_endpoint() {
+        let provider: LocalProvider = LocalProvider::new(
+            "http://127.0.0.1:9/v1/chat/completions".to_string(),
+            None,
+            LOCAL_LLM_MODEL.to_string(),
+            DEFAULT_TEMPERATURE,
+        );
+        let message: Message = Message {
+            role: "user".to_string(),
+            content: "Hi there, this is a test.".to_string(),
+        };
+
+        let res: Result<LlmResponse, AgentError> = provider.call(vec![message]).await;
+        assert!(res.is_err());
+        assert!(provider.name().contains(LOCAL_LLM_MODEL));
+    }
+
+    #[test]
+    fn tests_overrides_take_precedence() {
+        let overrides: ProviderOverrides = ProviderOverrides {
+            model: Some("llama3:70b".to_string()),
+            temperature: Some(0.7),
+        };
+        let provider: LocalProvider = LocalProvider::from_env(&overrides);
+        assert!(provider.name().contains("llama3:70b"));
+        assert_eq!(provider.temperature, 0.7);
+    }
+}
@@ -1 +1,8 @@
-pub mod call_request;
\ No newline at end of file
+pub mod call_request;
+pub mod context_budget;
+pub mod llm_provider;
+pub mod replay_provider;
+pub mod retry_policy;
+#[cfg(test)]
+pub mod scripted_provider;
+pub mod usage;
@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::llm_provider::LlmProvider;
+use crate::models::general::{
+    errors::AgentError,
+    llm::{LlmResponse, Message},
+};
+
+// FNV-1a parameters -- stable across Rust releases, unlike std's DefaultHasher
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayMode {
+    // Forward every call to the wrapped provider and save the exchange as a fixture
+    Record,
+    // Serve responses from fixtures only, never touching the network
+    Replay,
+}
+
+// A single recorded exchange, stored as `<fixtures_dir>/<prompt hash>.json`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LlmFixture {
+    pub messages: Vec<Message>,
+    pub response: String,
+}
+
+#[derive(Debug)]
+pub struct ReplayProvider {
+    fixtures_dir: PathBuf,
+    mode: ReplayMode,
+    inner: Option<Arc<dyn LlmProvider>>,
+}
+
+impl ReplayProvider {
+    pub fn record(fixtures_dir: PathBuf, inner: Arc<dyn LlmProvider>) -> Self {
+        Self {
+            fixtures_dir,
+            mode: ReplayMode::Record,
+            inner: Some(inner),
+        }
+    }
+
+    pub fn replay(fixtures_dir: PathBuf) -> Self {
+        Self {
+            fixtures_dir,
+            mode: ReplayMode::Replay,
+            inner: None,
+        }
+    }
+
+    pub fn fixture_path(&self, messages: &[Message]) -> PathBuf {
+        self.fixtures_dir
+            .join(format!("{}.json", prompt_hash(messages)))
+    }
+
+    fn read_fixture(&self, messages: &[Message]) -> Result<String, AgentError> {
+        let path: PathBuf = self.fixture_path(messages);
+        let contents: String = fs::read_to_string(&path).map_err(|e| {
+            AgentError::Config(format!(
+                "No LLM fixture at {} (re-record with LLM_FIXTURES_MODE=record): {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        let fixture: LlmFixture = serde_json::from_str(&contents)?;
+        Ok(fixture.response)
+    }
+
+    fn write_fixture(&self, messages: &[Message], response: &str) -> Result<(), AgentError> {
+        let fixture: LlmFixture = LlmFixture {
+            messages: messages.to_vec(),
+            response: response.to_string(),
+        };
+        let contents: String = serde_json::to_string_pretty(&fixture)?;
+
+        fs::create_dir_all(&self.fixtures_dir)?;
+        fs::write(self.fixture_path(messages), contents)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ReplayProvider {
+    fn name(&self) -> String {
+        match (&self.mode, &self.inner) {
+            (ReplayMode::Record, Some(inner)) => format!("Recording {}", inner.name()),
+            _ => format!("Replay ({})", self.fixtures_dir.display()),
+        }
+    }
+
+    // Replayed calls cost nothing, only recorded ones reach a model
+    fn model(&self) -> Option<String> {
+        match (&self.mode, &self.inner) {
+            (ReplayMode::Record, Some(inner)) => inner.model(),
+            _ => None,
+        }
+    }
+
+    async fn call(&self, messages: Vec<Message>) -> Result<LlmResponse, AgentError> {
+        return match (&self.mode, &self.inner) {
+            (ReplayMode::Record, Some(inner)) => {
+                let response: LlmResponse = inner.call(messages.clone()).await?;
+                self.write_fixture(&messages, &response.content)?;
+                Ok(response)
+            }
+            // Replayed calls cost nothing, so no usage is reported
+            _ => Ok(LlmResponse::text(self.read_fixture(&messages)?)),
+        };
+    }
+}
+
+// Stable hash over every message role and content, used as the fixture key
+pub fn prompt_hash(messages: &[Message]) -> String {
+    let mut hash: u64 = FNV_OFFSET_BASIS;
+    for message in messages {
+        for byte in message
+            .role
+            .bytes()
+            .chain([0u8])
+            .chain(message.content.bytes())
+            .chain([0u8])
+        {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+// Provider shared by the crate's tests: replays tests/fixtures/llm unless LLM_FIXTURES_MODE=record
+#[cfg(test)]
+pub fn test_provider() -> Arc<dyn LlmProvider> {
+    use super::llm_provider::{live_provider_from_env, ProviderOverrides};
+
+    let fixtures_dir: PathBuf =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/llm");
+    match std::env::var("LLM_FIXTURES_MODE").as_deref() {
+        Ok("record") => Arc::new(ReplayProvider::record(
+            fixtures_dir,
+            live_provider_from_env(&ProviderOverrides::default())
+                .expect("Failed to create live LLM provider for recording"),
+        )),
+        _ => Arc::new(ReplayProvider::replay(fixtures_dir)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct EchoProvider;
+
+    #[async_trait]
+    impl LlmProvider for EchoProvider {
+        fn name(&self) -> String {
+            "Echo".to_string()
+        }
+
+        async fn call(&self, messages: Vec<Message>) -> Result<LlmResponse, AgentError> {
+            return Ok(LlmResponse::text(messages[0].content.to_uppercase()));
+        }
+    }
+
+    fn message(content: &str) -> Message {
+        Message {
+            role: "system".to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn tests_prompt_hash_is_stable_and_order_sensitive() {
+        let a: Vec<Message> = vec![message("first"), message("second")];
+        let b: Vec<Message> = vec![message("second"), message("first")];
+
+        assert_eq!(prompt_hash(&a), prompt_hash(&a.clone()));
+        assert_ne!(prompt_hash(&a), prompt_hash(&b));
+        assert_eq!(prompt_hash(&a).len(), 16);
+    }
+
+    #[tokio::test]
+    async fn tests_record_then_replay() {
+        let fixtures_dir: PathBuf = std::env::temp_dir().join(format!(
+            "auto_gpt_replay_{}",
+            prompt_hash(&[message("tests_record_then_replay")])
+        ));
+        let _ = fs::remove_dir_all(&fixtures_dir);
+
+        let recorder: ReplayProvider =
+            ReplayProvider::record(fixtures_dir.clone(), Arc::new(EchoProvider));
+        let recorded: String = recorder.call(vec![message("hello")]).await.unwrap().content;
+        assert_eq!(recorded, "HELLO");
+
+        let replayer: ReplayProvider = ReplayProvider::replay(fixtures_dir.clone());
+        let replayed: String = replayer.call(vec![message("hello")]).await.unwrap().content;
+        assert_eq!(replayed, recorded);
+        assert!(replayer.call(vec![message("unknown")]).await.is_err());
+
+        let _ = fs::remove_dir_all(&fixtures_dir);
+    }
+}
@@ -0,0 +1,302 @@
+use async_trait::async_trait;
+use std::env;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::call_request::with_idle_timeout;
+use super::llm_provider::LlmProvider;
+use super::usage::{estimate_call, record_failed_attempt, reserve_spend, SpendReservation};
+use crate::helpers::command_line::PrintCommand;
+use crate::models::general::{
+    errors::AgentError,
+    llm::{LlmResponse, Message},
+};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+const DEFAULT_BASE_DELAY_MS: u64 = 1_000;
+const DEFAULT_MAX_DELAY_MS: u64 = 30_000;
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+
+// How hard an agent tries before giving up on a single LLM call
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    // Also caps the server's Retry-After
+    pub max_delay: Duration,
+    // Longest wait for the next piece of a response: a streamed completion runs for as long as it
+    // keeps sending, one that is not streamed arrives in one piece and has to be written in time
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: Duration::from_millis(DEFAULT_BASE_DELAY_MS),
+            max_delay: Duration::from_millis(DEFAULT_MAX_DELAY_MS),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Reads LLM_RETRY_* variables, each of which can be overridden per agent (i.e. FRONTEND_LLM_RETRY_MAX_ATTEMPTS)
+    pub fn from_env(agent: &str) -> Self {
+        let read = |key: &str| -> Option<u64> {
+            env::var(format!("{}_{}", agent.to_uppercase(), key))
+                .or_else(|_| env::var(key))
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+
+        let default: Self = Self::default();
+        Self {
+            max_attempts: read("LLM_RETRY_MAX_ATTEMPTS")
+                .map(|attempts| attempts.max(1) as u32)
+                .unwrap_or(default.max_attempts),
+            base_delay: read("LLM_RETRY_BASE_DELAY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(default.base_delay),
+            max_delay: read("LLM_RETRY_MAX_DELAY_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(default.max_delay),
+            timeout: read("LLM_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(default.timeout),
+        }
+    }
+
+    // Exponential backoff capped at max_delay, keeping between half and all of it (jitter)
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let exponent: u32 = attempt.saturating_sub(1).min(16);
+        let delay: Duration = self
+            .base_delay
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.max_delay);
+
+        let half_ms: u64 = delay.as_millis() as u64 / 2;
+        if half_ms == 0 {
+            return delay;
+        }
+        Duration::from_millis(half_ms + jitter_seed() % (half_ms + 1))
+    }
+
+    // None when the error should not be retried, otherwise how long to wait first
+    pub fn delay_before_retry(&self, error: &AgentError, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts || !is_retryable(error) {
+            return None;
+        }
+
+        // Rate limited: the server knows best how long to back off for, up to a point
+        if let AgentError::Http {
+            retry_after: Some(retry_after),
+            ..
+        } = error
+        {
+            return Some((*retry_after).min(self.max_delay));
+        }
+        Some(self.backoff_delay(attempt))
+    }
+}
+
+// Network failures, timeouts, rate limits and server errors are worth another try -- bad keys or requests are not
+pub fn is_retryable(error: &AgentError) -> bool {
+    match error {
+        AgentError::Transport(_) => true,
+        AgentError::Http { status, .. } => *status == 408 || *status == 429 || *status >= 500,
+        _ => false,
+    }
+}
+
+fn jitter_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos() as u64)
+        .unwrap_or(0)
+}
+
+// Wraps a provider so every call follows a retry policy
+#[derive(Debug)]
+pub struct RetryingProvider {
+    inner: Arc<dyn LlmProvider>,
+    policy: RetryPolicy,
+}
+
+impl RetryingProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, policy: RetryPolicy) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RetryingProvider {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    fn model(&self) -> Option<String> {
+        self.inner.model()
+    }
+
+    // The caller reserves the first attempt's spend (see call_and_record), every retry is checked
+    // against the budget again and every failed attempt is counted
+    async fn call(&self, messages: Vec<Message>) -> Result<LlmResponse, AgentError> {
+        let model: Option<String> = self.inner.model();
+        let mut attempt: u32 = 1;
+        loop {
+            let _reservation: Option<SpendReservation> = match attempt {
+                1 => None,
+                _ => Some(reserve_spend(estimate_call(model.as_deref(), &messages))?),
+            };
+            let error: AgentError =
+                match with_idle_timeout(self.policy.timeout, self.inner.call(messages.clone()))
+                    .await
+                {
+                    Ok(response) => return Ok(response),
+                    Err(e) => e,
+                };
+            record_failed_attempt(model.as_deref(), &messages, &error);
+
+            let delay: Duration = match self.policy.delay_before_retry(&error, attempt) {
+                Some(delay) => delay,
+                None => return Err(error),
+            };
+
+            PrintCommand::Issue.print_agent_message(
+                self.name().as_str(),
+                format!(
+                    "Attempt {}/{} failed ({}), retrying in {:?}",
+                    attempt, self.policy.max_attempts, error, delay
+                )
+                .as_str(),
+            );
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // Fails with each scripted error in turn, then answers
+    #[derive(Debug)]
+    struct FlakyProvider {
+        errors: Mutex<Vec<AgentError>>,
+        calls: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl LlmProvider for FlakyProvider {
+        fn name(&self) -> String {
+            "Flaky".to_string()
+        }
+
+        async fn call(&self, _messages: Vec<Message>) -> Result<LlmResponse, AgentError> {
+            *self.calls.lock().unwrap() += 1;
+            let mut errors = self.errors.lock().unwrap();
+            return match errors.is_empty() {
+                true => Ok(LlmResponse::text("recovered".to_string())),
+                false => Err(errors.remove(0)),
+            };
+        }
+    }
+
+    fn http_error(status: u16, retry_after: Option<Duration>) -> AgentError {
+        AgentError::Http {
+            status,
+            retry_after,
+            message: "dummy body".to_string(),
+        }
+    }
+
+    fn instant_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn tests_backoff_grows_and_is_capped() {
+        let policy: RetryPolicy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1_000),
+            timeout: Duration::from_secs(1),
+        };
+
+        let first: Duration = policy.backoff_delay(1);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+        let third: Duration = policy.backoff_delay(3);
+        assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+        assert!(policy.backoff_delay(30) <= Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn tests_retry_decisions() {
+        let policy: RetryPolicy = instant_policy(3);
+
+        let rate_limited: AgentError = http_error(429, Some(Duration::from_secs(7)));
+        let patient: RetryPolicy = RetryPolicy {
+            max_delay: Duration::from_secs(30),
+            ..instant_policy(3)
+        };
+        assert_eq!(
+            patient.delay_before_retry(&rate_limited, 1),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            patient.delay_before_retry(&http_error(429, Some(Duration::from_secs(86_400))), 1),
+            Some(Duration::from_secs(30))
+        );
+        assert!(policy.delay_before_retry(&rate_limited, 3).is_none());
+        assert!(policy
+            .delay_before_retry(&http_error(503, None), 1)
+            .is_some());
+        assert!(policy
+            .delay_before_retry(&http_error(401, None), 1)
+            .is_none());
+        assert!(policy
+            .delay_before_retry(&http_error(403, None), 1)
+            .is_none());
+        assert!(policy
+            .delay_before_retry(&AgentError::Decode("bad".to_string()), 1)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn tests_retrying_provider_recovers_from_transient_errors() {
+        let inner: Arc<FlakyProvider> = Arc::new(FlakyProvider {
+            errors: Mutex::new(vec![
+                AgentError::Transport("connection reset".to_string()),
+                http_error(429, Some(Duration::ZERO)),
+            ]),
+            calls: Mutex::new(0),
+        });
+        let provider: RetryingProvider = RetryingProvider::new(inner.clone(), instant_policy(3));
+
+        let res: String = provider.call(vec![]).await.unwrap().content;
+        assert_eq!(res, "recovered");
+        assert_eq!(*inner.calls.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn tests_retrying_provider_stops_on_auth_error() {
+        let inner: Arc<FlakyProvider> = Arc::new(FlakyProvider {
+            errors: Mutex::new(vec![http_error(401, None)]),
+            calls: Mutex::new(0),
+        });
+        let provider: RetryingProvider = RetryingProvider::new(inner.clone(), instant_policy(5));
+
+        let res: Result<LlmResponse, AgentError> = provider.call(vec![]).await;
+        assert!(matches!(res, Err(AgentError::Http { status: 401, .. })));
+        assert_eq!(*inner.calls.lock().unwrap(), 1);
+    }
+}
@@ -0,0 +1,135 @@
+use async_trait::async_trait;
+use std::sync::{Mutex, MutexGuard};
+
+use super::llm_provider::LlmProvider;
+use crate::models::general::{
+    errors::AgentError,
+    llm::{LlmResponse, Message},
+};
+
+// Answers every call with the canned response for the AI function in its prompt, so agent tests
+// run end to end without fixtures recorded from a live model. A function scripted more than once
+// gets its responses in turn, and the last one from then on
+#[derive(Debug)]
+pub struct ScriptedProvider {
+    // (AI function name, response)
+    responses: Mutex<Vec<(String, String)>>,
+    // Every conversation sent, in order
+    calls: Mutex<Vec<Vec<Message>>>,
+}
+
+impl ScriptedProvider {
+    pub fn new(responses: &[(&str, &str)]) -> Self {
+        Self {
+            responses: Mutex::new(
+                responses
+                    .iter()
+                    .map(|(function, response)| (function.to_string(), response.to_string()))
+                    .collect(),
+            ),
+            calls: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn calls(&self) -> Vec<Vec<Message>> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn response_for(&self, messages: &[Message]) -> Result<String, AgentError> {
+        // The prompt is the last system message, history and decode repairs come before and after it.
+        // Long function signatures are wrapped, so whitespace is collapsed before matching
+        let prompt: String = messages
+            .iter()
+            .rev()
+            .find(|message| message.role == "system")
+            .map(|message| {
+                message
+                    .content
+                    .split_whitespace()
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+
+        let mut responses: MutexGuard<Vec<(String, String)>> = self.responses.lock().unwrap();
+        let scripted: Vec<usize> = responses
+            .iter()
+            .enumerate()
+            .filter(|(_, (function, _))| prompt.contains(&format!("fn {}(", function)))
+            .map(|(index, _)| index)
+            .collect();
+        match scripted.as_slice() {
+            [] => Err(AgentError::Config(format!(
+                "No scripted response for prompt: {}",
+                prompt.chars().take(120).collect::<String>()
+            ))),
+            [last] => Ok(responses[*last].1.clone()),
+            [next, ..] => Ok(responses.remove(*next).1),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ScriptedProvider {
+    fn name(&self) -> String {
+        "Scripted".to_string()
+    }
+
+    async fn call(&self, messages: Vec<Message>) -> Result<LlmResponse, AgentError> {
+        let response: Result<String, AgentError> = self.response_for(&messages);
+        self.calls.lock().unwrap().push(messages);
+        Ok(LlmResponse::text(response?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai_functions::frontend::{
+        print_recommended_site_pages, print_recommended_site_pages_with_apis,
+    };
+    use crate::helpers::utils::extend_ai_function;
+
+    #[tokio::test]
+    async fn tests_responses_follow_the_ai_function() {
+        let llm: ScriptedProvider = ScriptedProvider::new(&[
+            ("print_recommended_site_pages", "pages"),
+            ("print_recommended_site_pages_with_apis", "routes"),
+        ]);
+
+        let pages: Message = extend_ai_function(print_recommended_site_pages, "a cat website");
+        let routes: Message =
+            extend_ai_function(print_recommended_site_pages_with_apis, "a cat website");
+        let repair: Message = Message {
+            role: "user".to_string(),
+            content: "Print ONLY the corrected JSON".to_string(),
+        };
+
+        assert_eq!(llm.call(vec![pages]).await.unwrap().content, "pages");
+        assert_eq!(
+            llm.call(vec![routes.clone(), repair])
+                .await
+                .unwrap()
+                .content,
+            "routes"
+        );
+        assert!(llm
+            .call(vec![Message {
+                role: "system".to_string(),
+                content: "FUNCTION fn print_svg_logo(".to_string(),
+            }])
+            .await
+            .is_err());
+        assert_eq!(llm.calls().len(), 3);
+
+        // Scripted twice: the answers come in turn, then the last one repeats
+        let llm: ScriptedProvider = ScriptedProvider::new(&[
+            ("print_recommended_site_pages", "first"),
+            ("print_recommended_site_pages", "second"),
+        ]);
+        for expected in ["first", "second", "second"] {
+            let pages: Message = extend_ai_function(print_recommended_site_pages, "a cat website");
+            assert_eq!(llm.call(vec![pages]).await.unwrap().content, expected);
+        }
+    }
+}
@@ -0,0 +1,543 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::{Mutex, MutexGuard};
+
+use super::context_budget::ContextBudget;
+use crate::helpers::command_line::PrintCommand;
+use crate::models::general::{
+    errors::AgentError,
+    llm::{LlmResponse, Message, TokenUsage},
+};
+
+// USD per 1K prompt and completion tokens, matched on the longest model name prefix
+// docs: https://openai.com/api/pricing
+const MODEL_PRICING: [(&str, f64, f64); 6] = [
+    ("gpt-4o-mini", 0.00015, 0.0006),
+    ("gpt-4o", 0.0025, 0.01),
+    ("gpt-4-turbo", 0.01, 0.03),
+    ("gpt-4-32k", 0.06, 0.12),
+    ("gpt-4", 0.03, 0.06),
+    ("gpt-3.5-turbo", 0.0005, 0.0015),
+];
+
+// Every LLM call made by this process, see call_and_record
+static USAGE_LEDGER: Mutex<UsageLedger> = Mutex::new(UsageLedger::new());
+static SPEND_BUDGET: Mutex<SpendBudget> = Mutex::new(SpendBudget::unlimited());
+
+// Hard limits on what a run may spend -- checked before every LLM call
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpendBudget {
+    pub max_tokens: Option<u64>,
+    pub max_cost_usd: Option<f64>,
+    pub max_calls: Option<u64>,
+}
+
+impl SpendBudget {
+    pub const fn unlimited() -> Self {
+        Self {
+            max_tokens: None,
+            max_cost_usd: None,
+            max_calls: None,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self == &Self::unlimited()
+    }
+
+    // Reads LLM_BUDGET_TOKENS, LLM_BUDGET_USD and LLM_BUDGET_CALLS
+    pub fn from_env() -> Result<Self, AgentError> {
+        let mut budget: Self = Self::unlimited();
+        for key in ["LLM_BUDGET_TOKENS", "LLM_BUDGET_USD", "LLM_BUDGET_CALLS"] {
+            if let Ok(value) = env::var(key) {
+                budget.set(key, &value)?;
+            }
+        }
+        Ok(budget)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), AgentError> {
+        let invalid = || AgentError::Config(format!("Invalid {} value: {}", key, value));
+        match key {
+            "LLM_BUDGET_USD" => {
+                self.max_cost_usd = Some(value.trim().parse::<f64>().map_err(|_| invalid())?)
+            }
+            "LLM_BUDGET_TOKENS" => {
+                self.max_tokens = Some(value.trim().parse::<u64>().map_err(|_| invalid())?)
+            }
+            _ => self.max_calls = Some(value.trim().parse::<u64>().map_err(|_| invalid())?),
+        }
+        Ok(())
+    }
+
+    // Fails once any limit has been reached, so the next call is never made
+    pub fn check(&self, spent: &UsageTotals) -> Result<(), AgentError> {
+        let tokens: u64 = spent.prompt_tokens + spent.completion_tokens;
+        if let Some(max_tokens) = self.max_tokens.filter(|max| tokens >= *max) {
+            return Err(AgentError::Budget(format!(
+                "{} of {} tokens spent",
+                tokens, max_tokens
+            )));
+        }
+        if let Some(max_cost_usd) = self.max_cost_usd.filter(|max| spent.cost_usd >= *max) {
+            return Err(AgentError::Budget(format!(
+                "${:.4} of ${:.4} spent",
+                spent.cost_usd, max_cost_usd
+            )));
+        }
+        if let Some(max_calls) = self.max_calls.filter(|max| spent.calls >= *max) {
+            return Err(AgentError::Budget(format!(
+                "{} of {} LLM calls made",
+                spent.calls, max_calls
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for SpendBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let limits: Vec<String> = [
+            self.max_tokens.map(|max| format!("{} tokens", max)),
+            self.max_cost_usd.map(|max| format!("${:.2}", max)),
+            self.max_calls.map(|max| format!("{} calls", max)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        match limits.is_empty() {
+            true => write!(f, "unlimited"),
+            false => write!(f, "{}", limits.join(", ")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageTotals {
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    const fn new() -> Self {
+        Self {
+            calls: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            cost_usd: 0.0,
+        }
+    }
+
+    fn add(&mut self, usage: &TokenUsage, cost_usd: f64) {
+        self.calls += 1;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.completion_tokens += usage.completion_tokens;
+        self.cost_usd += cost_usd;
+    }
+
+    fn add_totals(&mut self, totals: &UsageTotals) {
+        self.calls += totals.calls;
+        self.prompt_tokens += totals.prompt_tokens;
+        self.completion_tokens += totals.completion_tokens;
+        self.cost_usd += totals.cost_usd;
+    }
+
+    fn remove_totals(&mut self, totals: &UsageTotals) {
+        self.calls = self.calls.saturating_sub(totals.calls);
+        self.prompt_tokens = self.prompt_tokens.saturating_sub(totals.prompt_tokens);
+        self.completion_tokens = self
+            .completion_tokens
+            .saturating_sub(totals.completion_tokens);
+        self.cost_usd = (self.cost_usd - totals.cost_usd).max(0.0);
+    }
+}
+
+// Token spend broken down per agent position and per AI function
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageLedger {
+    pub total: UsageTotals,
+    pub by_agent: BTreeMap<String, UsageTotals>,
+    pub by_function: BTreeMap<String, UsageTotals>,
+    // Estimated spend of the calls in flight, counted against the budget until they are recorded
+    pub reserved: UsageTotals,
+    // Attempts the retry policy gave up on, already included in the total
+    pub failed_attempts: UsageTotals,
+}
+
+impl Default for UsageLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsageLedger {
+    pub const fn new() -> Self {
+        Self {
+            total: UsageTotals::new(),
+            by_agent: BTreeMap::new(),
+            by_function: BTreeMap::new(),
+            reserved: UsageTotals::new(),
+            failed_attempts: UsageTotals::new(),
+        }
+    }
+
+    // Fails once the recorded and reserved spend reach a limit, otherwise holds `estimate` against it
+    pub fn reserve(
+        &mut self,
+        budget: &SpendBudget,
+        estimate: &UsageTotals,
+    ) -> Result<(), AgentError> {
+        let mut committed: UsageTotals = self.total;
+        committed.add_totals(&self.reserved);
+        budget.check(&committed)?;
+
+        self.reserved.add_totals(estimate);
+        Ok(())
+    }
+
+    pub fn release(&mut self, estimate: &UsageTotals) {
+        self.reserved.remove_totals(estimate);
+    }
+
+    pub fn record(&mut self, agent_position: &str, function_name: &str, response: &LlmResponse) {
+        // Calls without usage (i.e. replayed fixtures) still count as calls
+        let usage: TokenUsage = response.usage.unwrap_or_default();
+        let cost_usd: f64 = match &response.model {
+            Some(model) => estimate_cost(model, &usage),
+            None => 0.0,
+        };
+
+        self.total.add(&usage, cost_usd);
+        self.by_agent
+            .entry(agent_position.to_string())
+            .or_default()
+            .add(&usage, cost_usd);
+        self.by_function
+            .entry(function_name.to_string())
+            .or_default()
+            .add(&usage, cost_usd);
+    }
+
+    pub fn record_failed_attempt(&mut self, model: Option<&str>, usage: &TokenUsage) {
+        let cost_usd: f64 = match model {
+            Some(model) => estimate_cost(model, usage),
+            None => 0.0,
+        };
+        self.total.add(usage, cost_usd);
+        self.failed_attempts.add(usage, cost_usd);
+    }
+
+    pub fn print(&self) {
+        let position: &str = "Cost Summary";
+        for (agent, totals) in &self.by_agent {
+            PrintCommand::Issue
+                .print_agent_message(position, format!("{} -- {}", agent, totals).as_str());
+        }
+        for (function_name, totals) in &self.by_function {
+            PrintCommand::Issue.print_agent_message(
+                position,
+                format!("{} -- {}", function_name, totals).as_str(),
+            );
+        }
+        if self.failed_attempts.calls > 0 {
+            PrintCommand::Issue.print_agent_message(
+                position,
+                format!("Failed attempts -- {}", self.failed_attempts).as_str(),
+            );
+        }
+        PrintCommand::Issue
+            .print_agent_message(position, format!("Total -- {}", self.total).as_str());
+    }
+}
+
+impl std::fmt::Display for UsageTotals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} calls, {} prompt + {} completion tokens, ${:.4}",
+            self.calls, self.prompt_tokens, self.completion_tokens, self.cost_usd
+        )
+    }
+}
+
+// Unknown and self-hosted models are counted as free
+pub fn estimate_cost(model: &str, usage: &TokenUsage) -> f64 {
+    match MODEL_PRICING
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+    {
+        Some((_, prompt_price, completion_price)) => {
+            (usage.prompt_tokens as f64 * prompt_price
+                + usage.completion_tokens as f64 * completion_price)
+                / 1000.0
+        }
+        None => 0.0,
+    }
+}
+
+// The spend of one call held against the budget while it is in flight, released when dropped
+#[derive(Debug)]
+pub struct SpendReservation {
+    estimate: Option<UsageTotals>,
+}
+
+impl SpendReservation {
+    fn release(&mut self, ledger: &mut UsageLedger) {
+        if let Some(estimate) = self.estimate.take() {
+            ledger.release(&estimate);
+        }
+    }
+}
+
+// A failed call spent nothing that is known, so its reservation is simply given back
+impl Drop for SpendReservation {
+    fn drop(&mut self) {
+        if self.estimate.is_some() {
+            let mut ledger: MutexGuard<UsageLedger> = USAGE_LEDGER
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            self.release(&mut ledger);
+        }
+    }
+}
+
+// Counted after the prompt is trimmed to the context window, as the provider will send it
+fn estimate_prompt_tokens(model: Option<&str>, messages: &[Message]) -> u64 {
+    let budget: ContextBudget = ContextBudget::for_model(model.unwrap_or_default());
+    let (messages, _): (Vec<Message>, Option<String>) = budget.fit(messages.to_vec());
+    budget.count_message_tokens(&messages) as u64
+}
+
+// One call with the prompt as sent, and an answer as long as the prompt (generated code often is)
+pub fn estimate_call(model: Option<&str>, messages: &[Message]) -> UsageTotals {
+    let prompt_tokens: u64 = estimate_prompt_tokens(model, messages);
+    let usage: TokenUsage = TokenUsage {
+        prompt_tokens,
+        completion_tokens: prompt_tokens,
+    };
+    let cost_usd: f64 = match model {
+        Some(model) => estimate_cost(model, &usage),
+        None => 0.0,
+    };
+
+    let mut estimate: UsageTotals = UsageTotals::new();
+    estimate.add(&usage, cost_usd);
+    estimate
+}
+
+// Replaces the call's reservation with what it actually spent
+pub fn record_usage(
+    mut reservation: SpendReservation,
+    agent_position: &str,
+    function_name: &str,
+    response: &LlmResponse,
+) {
+    let mut ledger: MutexGuard<UsageLedger> = USAGE_LEDGER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    reservation.release(&mut ledger);
+    ledger.record(agent_position, function_name, response);
+}
+
+// Counts an attempt that will not be recorded by call_and_record. Unless the server turned it away
+// with an error status, the prompt was most likely processed and billed
+pub fn record_failed_attempt(model: Option<&str>, messages: &[Message], error: &AgentError) {
+    let usage: TokenUsage = TokenUsage {
+        prompt_tokens: match error {
+            AgentError::Http { .. } => 0,
+            _ => estimate_prompt_tokens(model, messages),
+        },
+        completion_tokens: 0,
+    };
+    USAGE_LEDGER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .record_failed_attempt(model, &usage);
+}
+
+pub fn set_spend_budget(budget: SpendBudget) {
+    *SPEND_BUDGET
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = budget;
+}
+
+// Called before every LLM call, see call_and_record. The budget is checked and the call's estimate
+// reserved under one lock, so concurrent calls cannot all pass the check on the same totals
+pub fn reserve_spend(estimate: UsageTotals) -> Result<SpendReservation, AgentError> {
+    let budget: SpendBudget = *SPEND_BUDGET
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    USAGE_LEDGER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .reserve(&budget, &estimate)?;
+    Ok(SpendReservation {
+        estimate: Some(estimate),
+    })
+}
+
+pub fn usage_snapshot() -> UsageLedger {
+    return USAGE_LEDGER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(model: &str, prompt_tokens: u64, completion_tokens: u64) -> LlmResponse {
+        LlmResponse {
+            content: "dummy".to_string(),
+            model: Some(model.to_string()),
+            usage: Some(TokenUsage {
+                prompt_tokens,
+                completion_tokens,
+            }),
+        }
+    }
+
+    #[test]
+    fn tests_estimate_cost_matches_longest_prefix() {
+        let usage: TokenUsage = TokenUsage {
+            prompt_tokens: 1000,
+            completion_tokens: 1000,
+        };
+        assert!((estimate_cost("gpt-4-0613", &usage) - 0.09).abs() < 1e-9);
+        assert!((estimate_cost("gpt-4o-2024-08-06", &usage) - 0.0125).abs() < 1e-9);
+        assert_eq!(estimate_cost("llama3", &usage), 0.0);
+    }
+
+    #[test]
+    fn tests_ledger_accumulates_per_agent_and_function() {
+        let mut ledger: UsageLedger = UsageLedger::new();
+        ledger.record(
+            "Backend Developer",
+            "print_backend_webserver_code",
+            &response("gpt-4", 100, 50),
+        );
+        ledger.record(
+            "Backend Developer",
+            "print_fixed_code",
+            &response("gpt-4", 10, 5),
+        );
+        ledger.record(
+            "Solutions Architect",
+            "print_project_scope",
+            &LlmResponse::text("{}".to_string()),
+        );
+
+        assert_eq!(ledger.total.calls, 3);
+        assert_eq!(ledger.total.prompt_tokens, 110);
+        assert_eq!(ledger.by_agent["Backend Developer"].completion_tokens, 55);
+        assert_eq!(ledger.by_agent["Solutions Architect"].calls, 1);
+        assert_eq!(ledger.by_function["print_fixed_code"].prompt_tokens, 10);
+        assert!((ledger.total.cost_usd - (0.003 + 0.003 + 0.0003 + 0.0003)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tests_spend_budget_limits() {
+        let spent: UsageTotals = UsageTotals {
+            calls: 3,
+            prompt_tokens: 900,
+            completion_tokens: 100,
+            cost_usd: 0.05,
+        };
+
+        assert!(SpendBudget::unlimited().check(&spent).is_ok());
+
+        let mut budget: SpendBudget = SpendBudget::unlimited();
+        budget.set("LLM_BUDGET_CALLS", "4").unwrap();
+        assert!(budget.check(&spent).is_ok());
+
+        budget.set("LLM_BUDGET_TOKENS", "1000").unwrap();
+        assert!(matches!(budget.check(&spent), Err(AgentError::Budget(_))));
+
+        let budget: SpendBudget = SpendBudget {
+            max_cost_usd: Some(0.05),
+            ..SpendBudget::unlimited()
+        };
+        assert!(matches!(budget.check(&spent), Err(AgentError::Budget(_))));
+
+        assert!(SpendBudget::unlimited()
+            .set("LLM_BUDGET_USD", "lots")
+            .is_err());
+    }
+
+    #[test]
+    fn tests_calls_in_flight_count_against_the_budget() {
+        let budget: SpendBudget = SpendBudget {
+            max_calls: Some(3),
+            max_cost_usd: Some(0.09),
+            ..SpendBudget::unlimited()
+        };
+        let prompt: Vec<Message> = vec![Message {
+            role: "system".to_string(),
+            content: "x".repeat(3_500),
+        }];
+        let estimate: UsageTotals = estimate_call(Some("gpt-4"), &prompt);
+        assert_eq!(estimate.calls, 1);
+        assert_eq!(estimate.prompt_tokens, estimate.completion_tokens);
+        assert!((estimate.cost_usd - 0.09).abs() < 0.01);
+
+        // Prompts over the context window are estimated as they will be sent, trimmed
+        let oversized: Vec<Message> = vec![Message {
+            role: "system".to_string(),
+            content: "x".repeat(100_000),
+        }];
+        assert!(
+            estimate_call(Some("gpt-4"), &oversized).prompt_tokens
+                <= ContextBudget::for_model("gpt-4").max_prompt_tokens() as u64
+        );
+
+        // Two calls started together: the second sees the first one's reservation
+        let mut ledger: UsageLedger = UsageLedger::new();
+        assert!(ledger.reserve(&budget, &estimate).is_ok());
+        assert!(matches!(
+            ledger.reserve(&budget, &estimate),
+            Err(AgentError::Budget(_))
+        ));
+
+        // Once the first call is recorded, only what it really spent counts
+        ledger.release(&estimate);
+        ledger.record(
+            "Backend Developer",
+            "print_fixed_code",
+            &response("gpt-4", 100, 50),
+        );
+        assert_eq!(ledger.reserved, UsageTotals::new());
+        assert!(ledger.reserve(&budget, &estimate).is_ok());
+        assert!(matches!(
+            ledger.reserve(&budget, &estimate),
+            Err(AgentError::Budget(_))
+        ));
+    }
+
+    #[test]
+    fn tests_failed_attempts_count_against_the_budget() {
+        let budget: SpendBudget = SpendBudget {
+            max_calls: Some(2),
+            ..SpendBudget::unlimited()
+        };
+        let mut ledger: UsageLedger = UsageLedger::new();
+        let timed_out: TokenUsage = TokenUsage {
+            prompt_tokens: 1000,
+            completion_tokens: 0,
+        };
+        ledger.record_failed_attempt(Some("gpt-4"), &timed_out);
+        ledger.record_failed_attempt(None, &TokenUsage::default());
+
+        assert_eq!(ledger.failed_attempts.calls, 2);
+        assert_eq!(ledger.total, ledger.failed_attempts);
+        assert!((ledger.total.cost_usd - 0.03).abs() < 1e-9);
+        assert!(matches!(
+            ledger.reserve(&budget, &UsageTotals::new()),
+            Err(AgentError::Budget(_))
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+
+// Enough for the model to make progress without sending every cascading error
+const MAX_REPORTED_ERRORS: usize = 10;
+const MACHINE_APPLICABLE: &str = "MachineApplicable";
+
+// One line of `cargo build --message-format=json` output
+#[derive(Debug, Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<Diagnostic>,
+}
+
+// A rustc diagnostic, keeping only the fields the bug fixer uses
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub level: String,
+    #[serde(default)]
+    pub code: Option<DiagnosticCode>,
+    #[serde(default)]
+    pub spans: Vec<DiagnosticSpan>,
+    #[serde(default)]
+    pub children: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiagnosticCode {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: usize,
+    pub column_start: usize,
+    pub is_primary: bool,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub suggested_replacement: Option<String>,
+    #[serde(default)]
+    pub suggestion_applicability: Option<String>,
+    #[serde(default)]
+    pub text: Vec<DiagnosticSpanLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiagnosticSpanLine {
+    pub text: String,
+}
+
+// A replacement rustc is sure of, i.e. a missing `;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub file_name: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub replacement: String,
+}
+
+impl Diagnostic {
+    // Errors pointing at code, without "aborting due to ..." style summaries
+    pub fn is_code_error(&self) -> bool {
+        self.level == "error" && !self.spans.is_empty()
+    }
+
+    pub fn machine_applicable_suggestions(&self) -> Vec<Suggestion> {
+        let mut suggestions: Vec<Suggestion> = vec![];
+        for diagnostic in std::iter::once(self).chain(self.children.iter()) {
+            for span in &diagnostic.spans {
+                if let (Some(replacement), Some(MACHINE_APPLICABLE)) = (
+                    &span.suggested_replacement,
+                    span.suggestion_applicability.as_deref(),
+                ) {
+                    suggestions.push(Suggestion {
+                        file_name: span.file_name.clone(),
+                        byte_start: span.byte_start,
+                        byte_end: span.byte_end,
+                        replacement: replacement.clone(),
+                    });
+                }
+            }
+        }
+        suggestions
+    }
+
+    // "error[E0308]: mismatched types" followed by the code it points at and any help
+    pub fn summary(&self) -> String {
+        let mut lines: Vec<String> = vec![match &self.code {
+            Some(code) => format!("{}[{}]: {}", self.level, code.code, self.message),
+            None => format!("{}: {}", self.level, self.message),
+        }];
+        for span in &self.spans {
+            lines.push(format!(
+                "  --> {}:{}:{}{}",
+                span.file_name,
+                span.line_start,
+                span.column_start,
+                match &span.label {
+                    Some(label) => format!(" ({})", label),
+                    None => String::new(),
+                }
+            ));
+            for (offset, line) in span.text.iter().enumerate() {
+                lines.push(format!("{:>5} | {}", span.line_start + offset, line.text));
+            }
+        }
+        for child in &self.children {
+            let suggestion: Option<&String> = child
+                .spans
+                .iter()
+                .find_map(|span| span.suggested_replacement.as_ref());
+            lines.push(match suggestion {
+                Some(replacement) => format!(
+                    "  {}: {} `{}`",
+                    child.level,
+                    child.message,
+                    replacement.trim()
+                ),
+                None => format!("  {}: {}", child.level, child.message),
+            });
+        }
+        lines.join("\n")
+    }
+}
+
+// Compiler errors from the stdout of `cargo build --message-format=json`
+pub fn parse_diagnostics(stdout: &str) -> Vec<Diagnostic> {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+        .filter(|message| message.reason == "compiler-message")
+        .filter_map(|message| message.message)
+        .filter(|diagnostic| diagnostic.is_code_error())
+        .collect()
+}
+
+// Applies every machine applicable suggestion for `file_name`, None when there are none
+pub fn apply_suggestions(
+    source: &str,
+    file_name: &str,
+    diagnostics: &[Diagnostic],
+) -> Option<String> {
+    let mut suggestions: Vec<Suggestion> = diagnostics
+        .iter()
+        .flat_map(|diagnostic| diagnostic.machine_applicable_suggestions())
+        .filter(|suggestion| suggestion.file_name == file_name)
+        .collect();
+    if suggestions.is_empty() {
+        return None;
+    }
+
+    // From the end of the file backwards, so earlier byte offsets stay valid
+    suggestions.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.byte_start));
+    let mut fixed: String = source.to_string();
+    let mut applied_from: usize = usize::MAX;
+    for suggestion in suggestions {
+        let overlaps: bool = suggestion.byte_end > applied_from;
+        let in_bounds: bool = suggestion.byte_start <= suggestion.byte_end
+            && suggestion.byte_end <= fixed.len()
+            && fixed.is_char_boundary(suggestion.byte_start)
+            && fixed.is_char_boundary(suggestion.byte_end);
+        if overlaps || !in_bounds {
+            continue;
+        }
+        fixed.replace_range(
+            suggestion.byte_start..suggestion.byte_end,
+            &suggestion.replacement,
+        );
+        applied_from = suggestion.byte_start;
+    }
+    Some(fixed)
+}
+
+// What the bug fixing prompt gets instead of the raw compiler output
+pub fn bug_report(diagnostics: &[Diagnostic]) -> String {
+    let mut report: Vec<String> = diagnostics
+        .iter()
+        .take(MAX_REPORTED_ERRORS)
+        .map(|diagnostic| diagnostic.summary())
+        .collect();
+    if diagnostics.len() > MAX_REPORTED_ERRORS {
+        report.push(format!(
+            "... and {} more errors",
+            diagnostics.len() - MAX_REPORTED_ERRORS
+        ));
+    }
+    report.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed from `cargo build --message-format=json` output
+    const CARGO_OUTPUT: &str = r#"{"reason":"compiler-artifact","package_id":"serde 1.0.0"}
+{"reason":"compiler-message","message":{"children":[{"children":[],"code":null,"level":"help","message":"add `;` here","spans":[{"byte_end":25,"byte_start":25,"column_end":14,"column_start":14,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":";","suggestion_applicability":"MachineApplicable","text":[{"highlight_end":14,"highlight_start":14,"text":"    let a = 5"}]}]}],"level":"error","message":"expected `;`, found keyword `let`","spans":[{"byte_end":25,"byte_start":25,"column_end":14,"column_start":14,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[{"highlight_end":14,"highlight_start":14,"text":"    let a = 5"}]}],"code":null}}
+{"reason":"compiler-message","message":{"children":[],"level":"error","message":"mismatched types","spans":[{"byte_end":51,"byte_start":48,"column_end":21,"column_start":18,"file_name":"src/main.rs","is_primary":true,"label":"expected `u32`, found `&str`","line_end":3,"line_start":3,"suggested_replacement":null,"suggestion_applicability":null,"text":[{"highlight_end":21,"highlight_start":18,"text":"    let b: u32 = \"a\";"}]}],"code":{"code":"E0308","explanation":"Expected type did not match the received type."}}}
+{"reason":"compiler-message","message":{"children":[],"level":"error","message":"aborting due to 2 previous errors","spans":[],"code":null}}
+{"reason":"build-finished","success":false}"#;
+
+    #[test]
+    fn tests_cargo_diagnostics_to_fixes_and_report() {
+        let diagnostics: Vec<Diagnostic> = parse_diagnostics(CARGO_OUTPUT);
+        assert_eq!(diagnostics.len(), 2);
+
+        let source: &str = "fn main() {\n    let a = 5\n    let b: u32 = \"a\";\n}\n";
+        let fixed: String = apply_suggestions(source, "src/main.rs", &diagnostics).unwrap();
+        assert_eq!(
+            fixed,
+            "fn main() {\n    let a = 5;\n    let b: u32 = \"a\";\n}\n"
+        );
+        assert!(apply_suggestions(source, "src/lib.rs", &diagnostics).is_none());
+
+        let report: String = bug_report(&diagnostics);
+        assert!(report.contains("error[E0308]: mismatched types"));
+        assert!(report.contains("  --> src/main.rs:3:18 (expected `u32`, found `&str`)"));
+        assert!(report.contains("    3 |     let b: u32 = \"a\";"));
+        assert!(report.contains("  help: add `;` here `;`"));
+        assert!(!report.contains("aborting"));
+        assert!(!report.contains("Expected type did not match"));
+    }
+}
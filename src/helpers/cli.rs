@@ -0,0 +1,203 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use crate::apis::{llm_provider::ProviderOverrides, usage::SpendBudget};
+use crate::helpers::openapi::server_url_from_env;
+use crate::helpers::workspace::templates_dir_from_env;
+use crate::models::general::errors::AgentError;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "auto_gpt",
+    version,
+    about = "Builds full stack websites with a team of LLM agents"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Model to use instead of LLM_MODEL (i.e. gpt-4o, llama3)
+    #[arg(long, global = true)]
+    pub model: Option<String>,
+
+    /// Sampling temperature to use instead of LLM_TEMPERATURE
+    #[arg(long, global = true)]
+    pub temperature: Option<f32>,
+
+    /// Directory each run's workspace, checkpoint and saved fact sheet are created in
+    #[arg(long, global = true, default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Directory with the web_server and web_app templates (overrides AUTO_GPT_TEMPLATES_DIR)
+    #[arg(long, global = true)]
+    pub templates_dir: Option<PathBuf>,
+
+    /// URL the generated server is documented at in openapi.json (overrides AUTO_GPT_SERVER_URL)
+    #[arg(long, global = true)]
+    pub server_url: Option<String>,
+
+    /// Never prompt on stdin: a prompt is required and AI written code only runs with --allow-unreviewed-code
+    #[arg(long, global = true)]
+    pub non_interactive: bool,
+
+    /// Run AI written code without the safety confirmation
+    #[arg(long, global = true)]
+    pub allow_unreviewed_code: bool,
+
+    /// Do not echo model output as it streams in (implied by --non-interactive)
+    #[arg(long, global = true)]
+    pub quiet: bool,
+
+    /// Echo model output as it streams in, even with --non-interactive
+    #[arg(long, global = true, conflicts_with = "quiet")]
+    pub echo: bool,
+
+    /// Stop once this many tokens have been spent (overrides LLM_BUDGET_TOKENS)
+    #[arg(long, global = true)]
+    pub budget_tokens: Option<u64>,
+
+    /// Stop once this many dollars have been spent (overrides LLM_BUDGET_USD)
+    #[arg(long, global = true)]
+    pub budget_usd: Option<f64>,
+
+    /// Stop after this many LLM calls (overrides LLM_BUDGET_CALLS)
+    #[arg(long, global = true)]
+    pub budget_calls: Option<u64>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Build a new website (the default, asks on stdin when no prompt is given)
+    New { prompt: Vec<String> },
+
+    /// Pick a run back up from its checkpoint, skipping completed agents
+    Resume { checkpoint: PathBuf },
+
+    /// Only run the solutions architect
+    PlanOnly { prompt: Vec<String> },
+
+    /// Only run the backend developer, from a prompt or an existing fact sheet
+    BackendOnly {
+        prompt: Vec<String>,
+        #[arg(long)]
+        fact_sheet: Option<PathBuf>,
+    },
+
+    /// Only run the frontend developer on an existing fact sheet or checkpoint
+    FrontendOnly {
+        #[arg(long)]
+        fact_sheet: PathBuf,
+    },
+
+    /// Print what a checkpoint holds without calling any model
+    Inspect { checkpoint: PathBuf },
+}
+
+impl Cli {
+    pub fn provider_overrides(&self) -> ProviderOverrides {
+        ProviderOverrides {
+            model: self.model.clone(),
+            temperature: self.temperature,
+        }
+    }
+
+    // The command line directory, otherwise AUTO_GPT_TEMPLATES_DIR, otherwise the crate's own templates
+    pub fn templates_dir(&self) -> PathBuf {
+        self.templates_dir
+            .clone()
+            .unwrap_or_else(templates_dir_from_env)
+    }
+
+    // The command line URL, otherwise AUTO_GPT_SERVER_URL, otherwise the generated server's default port
+    pub fn server_url(&self) -> String {
+        self.server_url.clone().unwrap_or_else(server_url_from_env)
+    }
+
+    // Nobody watches a non-interactive run (i.e. in CI), so it only echoes when asked to
+    pub fn is_quiet(&self) -> bool {
+        self.quiet || (self.non_interactive && !self.echo)
+    }
+
+    // Environment limits with any command line limits on top
+    pub fn spend_budget(&self) -> Result<SpendBudget, AgentError> {
+        let mut budget: SpendBudget = SpendBudget::from_env()?;
+        budget.max_tokens = self.budget_tokens.or(budget.max_tokens);
+        budget.max_cost_usd = self.budget_usd.or(budget.max_cost_usd);
+        budget.max_calls = self.budget_calls.or(budget.max_calls);
+        Ok(budget)
+    }
+}
+
+// Prompt words are joined so quoting the prompt is optional
+pub fn join_prompt(prompt: &[String]) -> Option<String> {
+    let prompt: String = prompt.join(" ").trim().to_string();
+    match prompt.is_empty() {
+        true => None,
+        false => Some(prompt),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_parses_subcommands_and_global_flags() {
+        let cli: Cli = Cli::try_parse_from([
+            "auto_gpt",
+            "frontend-only",
+            "--fact-sheet",
+            "fact_sheet.json",
+            "--model",
+            "gpt-4o",
+            "--non-interactive",
+            "--allow-unreviewed-code",
+            "--quiet",
+            "--budget-usd",
+            "2.5",
+            "--templates-dir",
+            "/opt/auto_gpt/templates",
+            "--server-url",
+            "https://cats.example.com",
+        ])
+        .unwrap();
+
+        assert!(matches!(
+            cli.command,
+            Some(Command::FrontendOnly { ref fact_sheet }) if fact_sheet == &PathBuf::from("fact_sheet.json")
+        ));
+        assert_eq!(cli.provider_overrides().model, Some("gpt-4o".to_string()));
+        assert!(cli.non_interactive);
+        assert!(cli.allow_unreviewed_code);
+        assert!(cli.is_quiet());
+        assert_eq!(cli.spend_budget().unwrap().max_cost_usd, Some(2.5));
+        assert_eq!(
+            cli.templates_dir(),
+            PathBuf::from("/opt/auto_gpt/templates")
+        );
+        assert_eq!(cli.server_url(), "https://cats.example.com");
+
+        // Non-interactive runs are quiet unless --echo asks otherwise
+        let cli: Cli = Cli::try_parse_from(["auto_gpt", "--non-interactive"]).unwrap();
+        assert!(cli.is_quiet());
+        let cli: Cli = Cli::try_parse_from(["auto_gpt", "--non-interactive", "--echo"]).unwrap();
+        assert!(!cli.is_quiet());
+        assert!(Cli::try_parse_from(["auto_gpt", "--quiet", "--echo"]).is_err());
+
+        let cli: Cli =
+            Cli::try_parse_from(["auto_gpt", "new", "build", "me", "a", "todo", "app"]).unwrap();
+        assert!(!cli.is_quiet());
+        match cli.command {
+            Some(Command::New { prompt }) => {
+                assert_eq!(
+                    join_prompt(&prompt),
+                    Some("build me a todo app".to_string())
+                )
+            }
+            _ => panic!("expected the new subcommand"),
+        }
+        assert!(!cli.allow_unreviewed_code);
+
+        assert!(Cli::try_parse_from(["auto_gpt", "frontend-only"]).is_err());
+    }
+}
@@ -3,45 +3,144 @@ use crossterm::{
     ExecutableCommand,
 };
 
-use std::io::{stdin, stdout};
+use crate::models::general::errors::AgentError;
+
+use std::future::Future;
+use std::io::{stdin, stdout, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set by --non-interactive: nothing blocks on stdin, so runs can be scripted
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+// Set by --quiet: streamed completions are not echoed, i.e. to keep CI logs short
+static QUIET: AtomicBool = AtomicBool::new(false);
+// Set by --allow-unreviewed-code: AI written code runs without the safety confirmation
+static ALLOW_UNREVIEWED_CODE: AtomicBool = AtomicBool::new(false);
+
+tokio::task_local! {
+    // Cleared for tasks streaming alongside others, see with_stream_echo
+    static STREAM_ECHO: bool;
+}
 
 #[derive(PartialEq, Debug)]
 pub enum PrintCommand {
     AICall,
     UnitTest,
     Issue,
+    Stream,
 }
 
 impl PrintCommand {
-    pub fn print_agent_message(&self, agent_position: &str, agent_statement: &str) -> () {
+    pub fn print_agent_message(&self, agent_position: &str, agent_statement: &str) {
         let mut stdout: std::io::Stdout = stdout();
 
-        let statement_color: Color = match self {
-            Self::AICall => Color::Cyan,
-            Self::UnitTest => Color::Magenta,
-            Self::Issue => Color::Red,
-        };
-
         stdout.execute(SetForegroundColor(Color::Green)).unwrap();
         print!("Agent: {}: ", agent_position);
 
-        stdout.execute(SetForegroundColor(statement_color)).unwrap();
+        stdout.execute(SetForegroundColor(self.color())).unwrap();
         println!("{}", agent_statement);
 
         stdout.execute(ResetColor).unwrap();
     }
+
+    // Part of a completion as it streams in, without a newline
+    pub fn print_stream_token(&self, token: &str) {
+        if !is_stream_echoed() {
+            return;
+        }
+        let mut stdout: std::io::Stdout = stdout();
+
+        stdout.execute(SetForegroundColor(self.color())).unwrap();
+        print!("{}", token);
+        stdout.execute(ResetColor).unwrap();
+        stdout.flush().unwrap();
+    }
+
+    pub fn end_stream(&self) {
+        if is_stream_echoed() {
+            println!();
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::AICall => Color::Cyan,
+            Self::UnitTest => Color::Magenta,
+            Self::Issue => Color::Red,
+            Self::Stream => Color::DarkGrey,
+        }
+    }
+}
+
+pub fn get_user_input(prompt: &str) -> Result<String, AgentError> {
+    print_prompt_in_color(prompt, Color::Blue);
+    match read_user_input(&mut stdin().lock())? {
+        Some(user_response) => Ok(user_response.trim().to_string()),
+        None => Err(AgentError::MissingInput(
+            "stdin was closed before a response was entered".to_string(),
+        )),
+    }
+}
+
+pub fn set_non_interactive(non_interactive: bool) {
+    NON_INTERACTIVE.store(non_interactive, Ordering::Relaxed);
+}
+
+pub fn is_non_interactive() -> bool {
+    NON_INTERACTIVE.load(Ordering::Relaxed)
+}
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+// Runs `future` with its streamed tokens echoed only if `is_echoed` and the calling task echoes them,
+// i.e. off for calls made at the same time as others, whose tokens would be interleaved.
+// Decided here rather than when polled, as spawned tasks do not inherit task-locals
+pub fn with_stream_echo<F: Future>(is_echoed: bool, future: F) -> impl Future<Output = F::Output> {
+    STREAM_ECHO.scope(is_echoed && is_stream_echoed(), future)
+}
+
+// Tasks started outside with_stream_echo echo unless --quiet was passed
+pub fn is_stream_echoed() -> bool {
+    !is_quiet() && STREAM_ECHO.try_with(|is_echoed| *is_echoed).unwrap_or(true)
+}
+
+pub fn set_allow_unreviewed_code(allow_unreviewed_code: bool) {
+    ALLOW_UNREVIEWED_CODE.store(allow_unreviewed_code, Ordering::Relaxed);
 }
 
-pub fn get_user_input(prompt: &str) -> String {
-    print_prompt_in_color(&prompt, Color::Blue);
-    return read_user_input().trim().to_string();
+pub fn is_unreviewed_code_allowed() -> bool {
+    ALLOW_UNREVIEWED_CODE.load(Ordering::Relaxed)
 }
 
-pub fn confirm_safe_code() -> bool {
+pub fn confirm_safe_code() -> Result<bool, AgentError> {
     let mut stdout: std::io::Stdout = stdout();
+
+    // Whoever started the run has explicitly accepted running AI written code
+    if is_unreviewed_code_allowed() {
+        PrintCommand::Issue.print_agent_message(
+            "Safety Check",
+            "--allow-unreviewed-code: executing code written by AI without review",
+        );
+        return Ok(true);
+    }
+
+    // Nobody is there to review the code, and running it was not allowed up front
+    if is_non_interactive() {
+        PrintCommand::Issue.print_agent_message(
+            "Safety Check",
+            "Non-interactive mode: refusing to run code written by AI without review (pass --allow-unreviewed-code to run it)",
+        );
+        return Ok(false);
+    }
+
     loop {
         stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
-        println!("");
+        println!();
         println!("WARNING: You are about to run code entirely written by AI.");
         println!("Confirm your code and confirm you wish to continue.");
 
@@ -56,36 +155,38 @@ pub fn confirm_safe_code() -> bool {
 
         stdout.execute(ResetColor).unwrap();
 
-        let mut human_response = String::new();
-        stdin()
-            .read_line(&mut human_response)
-            .expect("Failed to read response");
-        let human_response = human_response.trim().to_lowercase();
+        // Nobody is there to answer (stdin closed), so do not run anything
+        let human_response: String = match read_user_input(&mut stdin().lock())? {
+            Some(human_response) => human_response.trim().to_lowercase(),
+            None => return Ok(false),
+        };
 
         match human_response.as_str() {
-            "1" | "ok" | "y" | "yes" => return true,
-            "2" | "no" | "n" | "abort" => return false,
+            "1" | "ok" | "y" | "yes" => return Ok(true),
+            "2" | "no" | "n" | "abort" => return Ok(false),
             _ => println!("Invalid response. Select '1' or '2'"),
         };
     }
 }
 
-fn print_prompt_in_color(prompt: &str, color: Color) -> () {
+fn print_prompt_in_color(prompt: &str, color: Color) {
     let mut stdout: std::io::Stdout = stdout();
 
     stdout.execute(SetForegroundColor(color)).unwrap();
-    println!("");
+    println!();
     println!("{}", prompt);
 
     stdout.execute(ResetColor).unwrap();
 }
 
-fn read_user_input() -> String {
+// One line from `input`, or None once it is closed
+fn read_user_input(input: &mut impl BufRead) -> Result<Option<String>, AgentError> {
     let mut user_response: String = String::new();
-    stdin()
-        .read_line(&mut user_response)
-        .expect("Failed to read response");
-    return user_response;
+    let bytes_read: usize = input.read_line(&mut user_response)?;
+    if bytes_read == 0 {
+        return Ok(None);
+    }
+    Ok(Some(user_response))
 }
 
 #[cfg(test)]
@@ -107,4 +208,39 @@ mod tests {
             "Testing testing, processing something in magenta",
         );
     }
+
+    #[test]
+    fn tests_read_user_input_reports_closed_and_failing_input() {
+        let mut answered: &[u8] = b"yes\n";
+        assert_eq!(
+            read_user_input(&mut answered).unwrap(),
+            Some("yes\n".to_string())
+        );
+
+        let mut closed: &[u8] = b"";
+        assert_eq!(read_user_input(&mut closed).unwrap(), None);
+
+        // Not valid UTF-8, so reading it fails instead of panicking
+        let mut unreadable: &[u8] = &[0xff, 0xfe, b'\n'];
+        assert!(matches!(
+            read_user_input(&mut unreadable),
+            Err(AgentError::Io(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn tests_stream_echo_is_per_task() {
+        assert!(is_stream_echoed());
+
+        let silenced = tokio::spawn(with_stream_echo(false, async {
+            // A task started from a silenced one cannot turn echoing back on
+            let nested = tokio::spawn(with_stream_echo(true, async { is_stream_echoed() }));
+            (is_stream_echoed(), nested.await.unwrap())
+        }));
+        let echoed = tokio::spawn(with_stream_echo(true, async { is_stream_echoed() }));
+
+        assert_eq!(silenced.await.unwrap(), (false, false));
+        assert!(echoed.await.unwrap());
+        assert!(is_stream_echoed());
+    }
 }
@@ -0,0 +1,196 @@
+use serde::de::DeserializeOwned;
+
+// Decodes a model response, cleaning up the usual slips (fences, commentary, comments, trailing commas)
+pub fn decode_llm_json<T: DeserializeOwned>(raw: &str) -> Result<T, serde_json::Error> {
+    if let Ok(decoded) = serde_json::from_str::<T>(raw) {
+        return Ok(decoded);
+    }
+
+    let cleaned: String = repair_json(raw);
+    serde_json::from_str::<T>(&cleaned)
+}
+
+pub fn repair_json(raw: &str) -> String {
+    let unfenced: &str = strip_code_fences(raw);
+    let extracted: &str = extract_json_value(unfenced);
+    let uncommented: String = remove_line_comments(extracted);
+    remove_trailing_commas(&uncommented)
+}
+
+// Keeps only what sits inside the first ``` fenced block, if there is one
+pub fn strip_code_fences(raw: &str) -> &str {
+    let start: usize = match raw.find("```") {
+        Some(start) => start,
+        None => return raw,
+    };
+
+    // Skip the language tag (i.e. ```json) up to the end of the fence line
+    let after_fence: &str = &raw[start + 3..];
+    let body: &str = match after_fence.find('\n') {
+        Some(newline) => &after_fence[newline + 1..],
+        None => after_fence,
+    };
+
+    match body.find("```") {
+        Some(end) => &body[..end],
+        None => body,
+    }
+}
+
+// Returns the first balanced JSON object or array, ignoring any commentary around it
+pub fn extract_json_value(raw: &str) -> &str {
+    let start: usize = match raw.find(['{', '[']) {
+        Some(start) => start,
+        None => return raw.trim(),
+    };
+
+    let mut depth: usize = 0;
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+
+    for (index, character) in raw[start..].char_indices() {
+        if in_string {
+            match character {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match character {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &raw[start..start + index + 1];
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Unbalanced: hand back everything from the opening bracket and let serde report it
+    raw[start..].trim()
+}
+
+// Drops `// ...` comments the model copies over from the function examples
+pub fn remove_line_comments(raw: &str) -> String {
+    let mut output: String = String::with_capacity(raw.len());
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+    let mut in_comment: bool = false;
+    let mut characters = raw.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        if in_comment {
+            if character == '\n' {
+                in_comment = false;
+                output.push(character);
+            }
+            continue;
+        }
+
+        if in_string {
+            match character {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            output.push(character);
+            continue;
+        }
+
+        match character {
+            '"' => in_string = true,
+            '/' if characters.peek() == Some(&'/') => {
+                in_comment = true;
+                continue;
+            }
+            _ => {}
+        }
+        output.push(character);
+    }
+
+    output
+}
+
+// Removes commas directly followed by a closing bracket, i.e. `[1, 2,]`
+pub fn remove_trailing_commas(raw: &str) -> String {
+    let characters: Vec<char> = raw.chars().collect();
+    let mut output: String = String::with_capacity(raw.len());
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+
+    for (index, character) in characters.iter().enumerate() {
+        if in_string {
+            match character {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            output.push(*character);
+            continue;
+        }
+
+        match character {
+            '"' => in_string = true,
+            ',' => {
+                let next: Option<&char> = characters[index + 1..]
+                    .iter()
+                    .find(|next| !next.is_whitespace());
+                if matches!(next, Some('}') | Some(']')) {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        output.push(*character);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::agents::agent_trait::ProjectScope;
+
+    #[test]
+    fn tests_decodes_fenced_json_with_commentary() {
+        let raw: &str = "Sure! Here is the scope:\n```json\n{\n  \"is_crud_required\": true,\n  \"is_user_login_and_logout\": false, // no users\n  \"is_external_urls_required\": true,\n}\n```\nLet me know if you need anything else.";
+
+        let decoded: ProjectScope = decode_llm_json(raw).unwrap();
+        assert!(decoded.is_crud_required);
+        assert!(!decoded.is_user_login_and_logout);
+    }
+
+    #[test]
+    fn tests_extracts_first_balanced_value() {
+        let raw: &str = "Output: [\"https://a.com/{id}\", \"b]\"] and then [\"ignored\"]";
+        assert_eq!(extract_json_value(raw), "[\"https://a.com/{id}\", \"b]\"]");
+    }
+
+    #[test]
+    fn tests_keeps_urls_and_commas_inside_strings() {
+        let raw: &str = "[\"https://api.binance.com/api/v3/klines\", \"a,]\",]";
+        let decoded: Vec<String> = decode_llm_json(raw).unwrap();
+        assert_eq!(
+            decoded,
+            vec![
+                "https://api.binance.com/api/v3/klines".to_string(),
+                "a,]".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_reports_unrecoverable_json() {
+        let res: Result<Vec<String>, serde_json::Error> = decode_llm_json("no json here");
+        assert!(res.is_err());
+    }
+}
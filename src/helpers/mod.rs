@@ -1,2 +1,9 @@
+pub mod cargo_diagnostics;
+pub mod cli;
 pub mod command_line;
+pub mod json_decode;
+pub mod openapi;
+pub mod route_analysis;
+pub mod server_process;
 pub mod utils;
+pub mod workspace;
@@ -0,0 +1,359 @@
+use serde_json::{json, Map, Value};
+use std::env;
+
+use crate::models::agents::agent_trait::RouteObject;
+
+const OPENAPI_VERSION: &str = "3.0.3";
+// Generated servers listen on 8080 unless given a PORT
+pub const DEFAULT_SERVER_URL: &str = "http://127.0.0.1:8080";
+const HTTP_METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+// AUTO_GPT_SERVER_URL, otherwise where a generated server listens by default
+pub fn server_url_from_env() -> String {
+    match env::var("AUTO_GPT_SERVER_URL") {
+        Ok(server_url) if !server_url.trim().is_empty() => server_url.trim().to_string(),
+        _ => DEFAULT_SERVER_URL.to_string(),
+    }
+}
+
+// OpenAPI 3 document for the generated server, fed to client generators and API gateways
+pub fn openapi_document(
+    project_description: &str,
+    server_url: &str,
+    endpoints: &[RouteObject],
+) -> Value {
+    let mut paths: Map<String, Value> = Map::new();
+    let mut schemas: Map<String, Value> = Map::new();
+
+    for endpoint in endpoints {
+        let method: String = endpoint.method.trim().to_lowercase();
+        if !HTTP_METHODS.contains(&method.as_str()) {
+            continue;
+        }
+
+        let path: String = openapi_path(&endpoint.route);
+        let operation_name: String = operation_name(&method, &path);
+        let mut operation: Map<String, Value> = Map::new();
+        operation.insert(
+            "operationId".to_string(),
+            json!(lower_first(&operation_name)),
+        );
+
+        let parameters: Vec<Value> = path_parameters(&path)
+            .into_iter()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" }
+                })
+            })
+            .collect();
+        if !parameters.is_empty() {
+            operation.insert("parameters".to_string(), Value::Array(parameters));
+        }
+
+        if let Some(schema) = body_schema(&endpoint.request_body) {
+            let name: String = format!("{}Request", operation_name);
+            schemas.insert(name.clone(), schema);
+            operation.insert(
+                "requestBody".to_string(),
+                json!({
+                    "required": true,
+                    "content": { "application/json": { "schema": schema_ref(&name) } }
+                }),
+            );
+        }
+
+        let response: Value = match body_schema(&endpoint.response) {
+            Some(schema) => {
+                let name: String = format!("{}Response", operation_name);
+                schemas.insert(name.clone(), schema);
+                json!({
+                    "description": "Successful response",
+                    "content": { "application/json": { "schema": schema_ref(&name) } }
+                })
+            }
+            None => json!({ "description": "Successful response" }),
+        };
+        operation.insert("responses".to_string(), json!({ "200": response }));
+
+        if let Value::Object(path_item) = paths.entry(path).or_insert(json!({})) {
+            path_item.insert(method, Value::Object(operation));
+        }
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "Generated web server",
+            "description": project_description,
+            "version": "1.0.0"
+        },
+        "servers": [{ "url": server_url }],
+        "paths": paths,
+        "components": { "schemas": schemas }
+    })
+}
+
+// Accepts both "/users/{id}" and "/users/:id" style segments
+fn openapi_path(route: &str) -> String {
+    let segments: Vec<String> = strip_parameter_patterns(route.trim())
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect();
+    format!("/{}", segments.join("/"))
+}
+
+// "/item/{id:\d+}" => "/item/{id}": actix allows a regex after the parameter name, OpenAPI path
+// templates only name the parameter. The regex may hold braces and slashes of its own
+fn strip_parameter_patterns(route: &str) -> String {
+    let mut path: String = String::new();
+    let mut depth: usize = 0;
+    let mut is_pattern: bool = false;
+    for c in route.chars() {
+        match (c, depth) {
+            ('{', 0) => {
+                depth = 1;
+                is_pattern = false;
+                path.push(c);
+            }
+            ('{', _) => depth += 1,
+            ('}', 1) => {
+                depth = 0;
+                path.push(c);
+            }
+            ('}', 0) => path.push(c),
+            ('}', _) => depth -= 1,
+            (':', 1) => is_pattern = true,
+            (_, 0) => path.push(c),
+            _ if !is_pattern => path.push(c),
+            _ => {}
+        }
+    }
+    path
+}
+
+// Names of the "{name}" segments of a path built by openapi_path
+fn path_parameters(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+// "get" + "/cats/{id}" => "GetCatsById"
+fn operation_name(method: &str, path: &str) -> String {
+    let mut name: String = pascal_case(method);
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(parameter) => {
+                name.push_str("By");
+                name.push_str(&pascal_case(parameter));
+            }
+            None => name.push_str(&pascal_case(segment)),
+        }
+    }
+    name
+}
+
+fn pascal_case(text: &str) -> String {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn lower_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+// None when the model said there is no body (null, "None", "", {})
+pub fn body_schema(example: &Value) -> Option<Value> {
+    match example {
+        Value::Null => None,
+        Value::String(text)
+            if matches!(text.trim().to_lowercase().as_str(), "" | "none" | "null") =>
+        {
+            None
+        }
+        Value::Object(fields) if fields.is_empty() => None,
+        _ => Some(infer_schema(example)),
+    }
+}
+
+// Example bodies hold either real values ({"id": 1}) or type names ({"id": "number"}). A null
+// example says nothing about the type, so it gets the empty schema, which accepts any value
+fn infer_schema(example: &Value) -> Value {
+    match example {
+        Value::Null => json!({}),
+        Value::Bool(_) => json!({ "type": "boolean" }),
+        Value::Number(number) if number.is_f64() => json!({ "type": "number" }),
+        Value::Number(_) => json!({ "type": "integer" }),
+        Value::String(text) => type_name_schema(text),
+        Value::Array(items) => match items.first() {
+            Some(item) => json!({ "type": "array", "items": infer_schema(item) }),
+            None => json!({ "type": "array", "items": {} }),
+        },
+        Value::Object(fields) => {
+            let properties: Map<String, Value> = fields
+                .iter()
+                .map(|(name, value)| (name.clone(), infer_schema(value)))
+                .collect();
+            // A field given as null or as an optional type may well be left out
+            let required: Vec<&String> = properties
+                .iter()
+                .filter(|(name, schema)| !fields[*name].is_null() && schema["nullable"] != true)
+                .map(|(name, _)| name)
+                .collect();
+            json!({ "type": "object", "properties": properties, "required": required })
+        }
+    }
+}
+
+fn type_name_schema(text: &str) -> Value {
+    if let Some(inner) = optional_type_name(text) {
+        let mut schema: Value = type_name_schema(inner);
+        schema["nullable"] = Value::Bool(true);
+        return schema;
+    }
+    let type_name: String = text.trim().to_lowercase();
+    match type_name.as_str() {
+        "string" | "str" | "text" | "date" | "datetime" => json!({ "type": "string" }),
+        "number" | "float" | "f32" | "f64" | "double" | "decimal" => json!({ "type": "number" }),
+        "integer" | "int" | "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64"
+        | "usize" => json!({ "type": "integer" }),
+        "boolean" | "bool" => json!({ "type": "boolean" }),
+        "array" | "list" | "vec" => json!({ "type": "array", "items": {} }),
+        "object" | "json" | "map" => json!({ "type": "object" }),
+        _ => json!({ "type": "string", "example": text }),
+    }
+}
+
+// "Option<string>", "string?", "string | null", "optional string" => "string"
+fn optional_type_name(text: &str) -> Option<&str> {
+    let text: &str = text.trim();
+    let lower: String = text.to_lowercase();
+    let inner: &str = if lower.starts_with("option<") && lower.ends_with('>') {
+        &text["option<".len()..text.len() - 1]
+    } else if let Some(inner) = text.strip_suffix('?') {
+        inner
+    } else if lower.ends_with("| null") {
+        &text[..text.len() - "| null".len()]
+    } else if lower.starts_with("optional ") || lower.starts_with("nullable ") {
+        &text["optional ".len()..]
+    } else {
+        return None;
+    };
+    match inner.trim() {
+        "" => None,
+        inner => Some(inner),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(method: &str, route: &str, request_body: Value, response: Value) -> RouteObject {
+        RouteObject {
+            route: route.to_string(),
+            is_route_dynamic: route.contains('{') || route.contains(':'),
+            method: method.to_string(),
+            request_body,
+            response,
+        }
+    }
+
+    #[test]
+    fn tests_openapi_document_from_routes() {
+        let endpoints: Vec<RouteObject> = vec![
+            route(
+                "get",
+                "/cats/{id}",
+                json!("None"),
+                json!({ "id": "number", "url": "string", "tags": ["string"] }),
+            ),
+            route(
+                "PUT",
+                "/cats/:id",
+                json!({ "id": 1, "url": "https://cdn2.thecatapi.com/1.jpg", "cute": true }),
+                Value::Null,
+            ),
+            route("get", "/cats", Value::Null, json!("Array")),
+            route("connect", "/tunnel", Value::Null, Value::Null),
+            route(
+                "get",
+                r"/files/{name:[a-z]{2}/.*}/{version:\d+}",
+                Value::Null,
+                json!({ "name": "string", "owner": null, "size": "Option<u64>" }),
+            ),
+        ];
+
+        let document: Value = openapi_document("cat pictures", "http://127.0.0.1:3000", &endpoints);
+        assert_eq!(document["openapi"], "3.0.3");
+        assert_eq!(document["servers"][0]["url"], "http://127.0.0.1:3000");
+        assert!(document["paths"].get("/tunnel").is_none());
+
+        // The actix regex is not part of the OpenAPI path template
+        let file: &Value = &document["paths"]["/files/{name}/{version}"]["get"];
+        assert_eq!(file["operationId"], "getFilesByNameByVersion");
+        assert_eq!(file["parameters"][0]["name"], "name");
+        assert_eq!(file["parameters"][1]["name"], "version");
+        let file_response: &Value =
+            &document["components"]["schemas"]["GetFilesByNameByVersionResponse"];
+        assert_eq!(file_response["properties"]["owner"], json!({}));
+        assert_eq!(
+            file_response["properties"]["size"],
+            json!({ "type": "integer", "nullable": true })
+        );
+        assert_eq!(optional_type_name("string | null"), Some("string"));
+        assert_eq!(optional_type_name("optional bool"), Some("bool"));
+        assert_eq!(optional_type_name("string"), None);
+        assert_eq!(file_response["required"], json!(["name"]));
+
+        let cat: &Value = &document["paths"]["/cats/{id}"];
+        assert_eq!(cat["get"]["operationId"], "getCatsById");
+        assert_eq!(cat["get"]["parameters"][0]["name"], "id");
+        assert_eq!(cat["get"]["parameters"][0]["in"], "path");
+        assert!(cat["get"].get("requestBody").is_none());
+        assert_eq!(
+            cat["get"]["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/GetCatsByIdResponse"
+        );
+        assert_eq!(
+            cat["put"]["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/PutCatsByIdRequest"
+        );
+
+        let schemas: &Value = &document["components"]["schemas"];
+        let response: &Value = &schemas["GetCatsByIdResponse"];
+        assert_eq!(response["properties"]["id"]["type"], "number");
+        assert_eq!(response["properties"]["tags"]["items"]["type"], "string");
+        let request: &Value = &schemas["PutCatsByIdRequest"];
+        assert_eq!(request["properties"]["id"]["type"], "integer");
+        assert_eq!(request["properties"]["cute"]["type"], "boolean");
+        assert_eq!(schemas["GetCatsResponse"]["type"], "array");
+    }
+}
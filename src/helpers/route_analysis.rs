@@ -0,0 +1,469 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use syn::punctuated::Punctuated;
+use syn::visit::{self, Visit};
+use syn::{Expr, ExprMethodCall, FnArg, ItemConst, ItemFn, Lit, Pat, Token, Type};
+
+use crate::models::{agents::agent_trait::RouteObject, general::errors::AgentError};
+
+// Methods with a `web::<method>()` route helper and a `#[<method>("/path")]` handler macro
+const HTTP_METHODS: [&str; 8] = [
+    "get", "post", "put", "patch", "delete", "head", "options", "trace",
+];
+
+// Example bodies the LLM writes for one extracted route
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RouteExample {
+    pub route: String,
+    pub method: String,
+    pub request_body: Value,
+    pub response: Value,
+}
+
+// Routes registered in generated actix-web code, in registration order and without bodies
+pub fn extract_routes(source: &str) -> Result<Vec<RouteObject>, AgentError> {
+    let file: syn::File = match syn::parse_file(source) {
+        Ok(file) => file,
+        Err(e) => {
+            return Err(AgentError::Decode(format!(
+                "Unable to parse web server code: {}",
+                e
+            )))
+        }
+    };
+
+    let mut index: SourceIndex = SourceIndex::default();
+    index.visit_file(&file);
+
+    let mut finder: RouteFinder = RouteFinder {
+        index: &index,
+        config_params: vec![],
+        routes: vec![],
+    };
+    finder.visit_file(&file);
+
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    let mut routes: Vec<RouteObject> = vec![];
+    for (route, method) in finder.routes {
+        if seen.insert((route.clone(), method.clone())) {
+            routes.push(RouteObject {
+                is_route_dynamic: route.contains('{'),
+                route,
+                method,
+                request_body: Value::Null,
+                response: Value::Null,
+            });
+        }
+    }
+    Ok(routes)
+}
+
+// Copies the LLM's example bodies onto the extracted routes, ignoring any route it made up
+pub fn fill_example_bodies(routes: &mut [RouteObject], examples: &[RouteExample]) {
+    for route in routes.iter_mut() {
+        let example: Option<&RouteExample> = examples.iter().find(|example| {
+            example.route == route.route && example.method.eq_ignore_ascii_case(&route.method)
+        });
+        if let Some(example) = example {
+            route.request_body = example.request_body.clone();
+            route.response = example.response.clone();
+        }
+    }
+}
+
+// String constants and attribute macro handlers that route registrations refer to
+#[derive(Default)]
+struct SourceIndex {
+    consts: HashMap<String, String>,
+    handlers: HashMap<String, Vec<(String, String)>>,
+}
+
+impl<'ast> Visit<'ast> for SourceIndex {
+    fn visit_item_const(&mut self, item: &'ast ItemConst) {
+        if let Some(value) = string_literal(&item.expr) {
+            self.consts.insert(item.ident.to_string(), value);
+        }
+        visit::visit_item_const(self, item);
+    }
+
+    // i.e. #[get("/task/{id}")] or #[route("/task", method = "GET", method = "HEAD")]
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        for attr in &item.attrs {
+            let macro_name: String = match attr.path().segments.last() {
+                Some(segment) => segment.ident.to_string(),
+                None => continue,
+            };
+            if macro_name != "route" && !HTTP_METHODS.contains(&macro_name.as_str()) {
+                continue;
+            }
+            let args: Punctuated<Expr, Token![,]> =
+                match attr.parse_args_with(Punctuated::parse_terminated) {
+                    Ok(args) => args,
+                    Err(_) => continue,
+                };
+            let path: String = match args.first().and_then(string_literal) {
+                Some(path) => path,
+                None => continue,
+            };
+
+            let methods: Vec<String> = match macro_name.as_str() {
+                "route" => args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        Expr::Assign(assign) if is_ident(&assign.left, "method") => {
+                            string_literal(&assign.right).map(|method| method.to_lowercase())
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![macro_name],
+            };
+            let registrations: &mut Vec<(String, String)> =
+                self.handlers.entry(item.sig.ident.to_string()).or_default();
+            for method in methods {
+                registrations.push((path.clone(), method));
+            }
+        }
+        visit::visit_item_fn(self, item);
+    }
+}
+
+struct RouteFinder<'a> {
+    index: &'a SourceIndex,
+    // Names of `&mut web::ServiceConfig` parameters of the function being visited
+    config_params: Vec<String>,
+    routes: Vec<(String, String)>,
+}
+
+impl<'a, 'ast> Visit<'ast> for RouteFinder<'a> {
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        let depth: usize = self.config_params.len();
+        for input in &item.sig.inputs {
+            if let FnArg::Typed(param) = input {
+                if let (Pat::Ident(name), Type::Reference(reference)) =
+                    (param.pat.as_ref(), param.ty.as_ref())
+                {
+                    if last_segment(&reference.elem).as_deref() == Some("ServiceConfig") {
+                        self.config_params.push(name.ident.to_string());
+                    }
+                }
+            }
+        }
+        visit::visit_item_fn(self, item);
+        self.config_params.truncate(depth);
+    }
+
+    // Registrations are chained onto `App::new()` or onto a `cfg: &mut web::ServiceConfig`
+    fn visit_expr_method_call(&mut self, node: &'ast ExprMethodCall) {
+        let (root, calls) = method_chain(node);
+        let is_app: bool = match root {
+            Expr::Call(call) => path_name(&call.func).ends_with("App::new"),
+            _ => false,
+        };
+        let is_config: bool = self.config_params.iter().any(|param| is_ident(root, param));
+        if is_app || is_config {
+            self.register_chain(&calls, "");
+            return;
+        }
+        visit::visit_expr_method_call(self, node);
+    }
+}
+
+impl<'a> RouteFinder<'a> {
+    // `.route("/path", web::get().to(handler))` and `.service(...)` calls of an App or Scope
+    fn register_chain(&mut self, calls: &[&ExprMethodCall], prefix: &str) {
+        for call in calls {
+            let args: Vec<&Expr> = call.args.iter().collect();
+            match (call.method.to_string().as_str(), args.as_slice()) {
+                ("route", [path, route]) => {
+                    if let Some(path) = self.path_argument(path) {
+                        for method in route_methods(route) {
+                            self.routes.push((join_path(prefix, &path), method));
+                        }
+                    }
+                }
+                ("service", [service]) => self.register_service(service, prefix),
+                _ => {}
+            }
+        }
+    }
+
+    fn register_service(&mut self, service: &Expr, prefix: &str) {
+        match service {
+            Expr::Paren(inner) => self.register_service(&inner.expr, prefix),
+            Expr::Reference(inner) => self.register_service(&inner.expr, prefix),
+            Expr::Tuple(tuple) => {
+                for element in &tuple.elems {
+                    self.register_service(element, prefix);
+                }
+            }
+
+            // Handlers registered through an attribute macro, i.e. `.service(read_task)`
+            Expr::Path(path) => {
+                let name: String = match path.path.segments.last() {
+                    Some(segment) => segment.ident.to_string(),
+                    None => return,
+                };
+                if let Some(registrations) = self.index.handlers.get(&name) {
+                    for (path, method) in registrations {
+                        self.routes.push((join_path(prefix, path), method.clone()));
+                    }
+                }
+            }
+
+            Expr::Call(_) | Expr::MethodCall(_) => {
+                let (root, calls) = match service {
+                    Expr::MethodCall(call) => method_chain(call),
+                    _ => (service, vec![]),
+                };
+                let constructor = match root {
+                    Expr::Call(call) => call,
+                    _ => return,
+                };
+                let path: String = match constructor
+                    .args
+                    .first()
+                    .and_then(|arg| self.path_argument(arg))
+                {
+                    Some(path) => path,
+                    None => return,
+                };
+                let constructor_name: String = path_name(&constructor.func);
+
+                // web::scope("/api").route(...).service(...)
+                if constructor_name.ends_with("scope") || constructor_name.ends_with("Scope::new") {
+                    self.register_chain(&calls, &join_path(prefix, &path));
+                    return;
+                }
+
+                // web::resource("/task").route(web::get().to(...)).route(web::post().to(...))
+                if constructor_name.ends_with("resource")
+                    || constructor_name.ends_with("Resource::new")
+                {
+                    let route: String = join_path(prefix, &path);
+                    for call in &calls {
+                        match (call.method.to_string().as_str(), call.args.first()) {
+                            ("route", Some(arg)) => {
+                                for method in route_methods(arg) {
+                                    self.routes.push((route.clone(), method));
+                                }
+                            }
+                            // `.to(handler)` answers every method
+                            ("to", Some(_)) => self.routes.push((route.clone(), "get".to_string())),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn path_argument(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Path(path) => match path.path.get_ident() {
+                Some(ident) => self.index.consts.get(&ident.to_string()).cloned(),
+                None => None,
+            },
+            _ => string_literal(expr),
+        }
+    }
+}
+
+// `web::get().to(h)`, `web::method(Method::PUT).to(h)` or `web::route().guard(guard::Post()).to(h)`
+fn route_methods(route: &Expr) -> Vec<String> {
+    let (root, calls) = match route {
+        Expr::MethodCall(call) => method_chain(call),
+        _ => (route, vec![]),
+    };
+
+    let mut methods: Vec<String> = vec![];
+    if let Expr::Call(call) = root {
+        let name: String = path_name(&call.func).to_lowercase();
+        let helper: &str = name.rsplit("::").next().unwrap_or(&name);
+        if HTTP_METHODS.contains(&helper) {
+            methods.push(helper.to_string());
+        } else if helper == "method" {
+            methods.extend(call.args.first().and_then(http_method));
+        }
+    }
+    for call in calls {
+        if call.method == "method" || call.method == "guard" {
+            methods.extend(call.args.first().and_then(http_method));
+        }
+    }
+
+    // A route without a method guard answers every method
+    if methods.is_empty() {
+        methods.push("get".to_string());
+    }
+    methods
+}
+
+// `Method::POST`, `http::Method::POST` or `guard::Post()` => "post"
+fn http_method(expr: &Expr) -> Option<String> {
+    let name: String = match expr {
+        Expr::Path(_) => path_name(expr),
+        Expr::Call(call) => path_name(&call.func),
+        _ => return None,
+    };
+    let method: String = name.rsplit("::").next()?.to_lowercase();
+    match HTTP_METHODS.contains(&method.as_str()) {
+        true => Some(method),
+        false => None,
+    }
+}
+
+// `App::new().app_data(..).route(..)` => (`App::new()`, [app_data, route])
+fn method_chain(node: &ExprMethodCall) -> (&Expr, Vec<&ExprMethodCall>) {
+    let mut calls: Vec<&ExprMethodCall> = vec![node];
+    let mut receiver: &Expr = &node.receiver;
+    while let Expr::MethodCall(call) = receiver {
+        calls.push(call);
+        receiver = &call.receiver;
+    }
+    calls.reverse();
+    (receiver, calls)
+}
+
+fn path_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Path(path) => path
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<String>>()
+            .join("::"),
+        _ => String::new(),
+    }
+}
+
+fn last_segment(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn is_ident(expr: &Expr, name: &str) -> bool {
+    match expr {
+        Expr::Path(path) => path.path.is_ident(name),
+        _ => false,
+    }
+}
+
+fn string_literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Str(text) => Some(text.value()),
+            _ => None,
+        },
+        Expr::Reference(reference) => string_literal(&reference.expr),
+        _ => None,
+    }
+}
+
+// join_path("/api", "/cats/") => "/api/cats"
+fn join_path(prefix: &str, path: &str) -> String {
+    let joined: String = format!(
+        "{}/{}",
+        prefix.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    );
+    match joined.len() > 1 {
+        true => joined.trim_end_matches('/').to_string(),
+        false => joined,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE_SERVER: &str = include_str!("../templates/web_server/src/code_template.rs");
+
+    #[test]
+    fn tests_routes_extracted_from_actix_code() {
+        let template_routes: Vec<RouteObject> = extract_routes(TEMPLATE_SERVER).unwrap();
+        assert_eq!(template_routes.len(), 7);
+        assert_eq!(template_routes[3].route, "/task/{id}");
+        assert_eq!(template_routes[3].method, "get");
+        assert!(template_routes[3].is_route_dynamic);
+
+        let source: &str = r#"
+            const CATS_PATH: &str = "/cats";
+
+            #[get("/health")]
+            async fn health() -> impl Responder { HttpResponse::Ok().finish() }
+
+            #[route("/echo", method = "POST", method = "PUT")]
+            async fn echo() -> impl Responder { HttpResponse::Ok().finish() }
+
+            fn config(cfg: &mut web::ServiceConfig) {
+                cfg.service(web::resource("/cats/{id}")
+                    .route(web::get().to(read_cat))
+                    .route(web::method(Method::DELETE).to(delete_cat)));
+            }
+
+            #[actix_web::main]
+            async fn main() -> std::io::Result<()> {
+                return HttpServer::new(move || {
+                    App::new()
+                        .configure(config)
+                        .service((health, echo))
+                        .service(web::scope("/api/").route(CATS_PATH, web::post().to(create_cat)))
+                        .route("/cats", web::route().guard(guard::Patch()).to(patch_cats))
+                })
+                .bind("127.0.0.1:8080")?
+                .run()
+                .await;
+            }
+        "#;
+
+        let mut routes: Vec<RouteObject> = extract_routes(source).unwrap();
+        let registered: Vec<(&str, &str)> = routes
+            .iter()
+            .map(|route| (route.route.as_str(), route.method.as_str()))
+            .collect();
+        assert_eq!(
+            registered,
+            vec![
+                ("/cats/{id}", "get"),
+                ("/cats/{id}", "delete"),
+                ("/health", "get"),
+                ("/echo", "post"),
+                ("/echo", "put"),
+                ("/api/cats", "post"),
+                ("/cats", "patch"),
+            ]
+        );
+
+        // The LLM only writes bodies, routes it invents are dropped
+        let examples: Vec<RouteExample> = vec![
+            RouteExample {
+                route: "/api/cats".to_string(),
+                method: "POST".to_string(),
+                request_body: serde_json::json!({ "url": "string" }),
+                response: Value::String("None".to_string()),
+            },
+            RouteExample {
+                route: "/dogs".to_string(),
+                method: "get".to_string(),
+                request_body: Value::Null,
+                response: Value::Null,
+            },
+        ];
+        fill_example_bodies(&mut routes, &examples);
+        assert_eq!(routes.len(), 7);
+        assert_eq!(routes[5].request_body["url"], "string");
+        assert_eq!(routes[0].request_body, Value::Null);
+
+        assert!(extract_routes("fn main( {").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tokio::{net::TcpStream, runtime::Handle, task, time};
+
+use crate::models::general::errors::AgentError;
+
+// Older lines are dropped from memory, the log file keeps everything
+const LOG_LINES_KEPT: usize = 2000;
+const PROBE_INTERVAL: Duration = Duration::from_millis(250);
+// Processes the server started may still hold its pipes open after it is killed
+const LOG_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// A running generated web server, killed when dropped (including while a panic unwinds)
+#[derive(Debug)]
+pub struct ServerProcess {
+    // None once the server has been stopped
+    child: Option<Child>,
+    pid: u32,
+    pub port: u16,
+    logs: Arc<Mutex<Vec<String>>>,
+    log_readers: Vec<JoinHandle<()>>,
+}
+
+// A port nothing is listening on right now, for the server to bind
+pub fn free_port() -> Result<u16, AgentError> {
+    let listener: TcpListener = TcpListener::bind(("127.0.0.1", 0))?;
+    Ok(listener.local_addr()?.port())
+}
+
+impl ServerProcess {
+    // The server is told which port to bind through the PORT environment variable
+    pub fn start(mut command: Command, port: u16, log_path: &Path) -> Result<Self, AgentError> {
+        // Everything that can fail happens before the spawn, so no server is left without an owner
+        let log_file: Arc<Mutex<File>> = Arc::new(Mutex::new(File::create(log_path)?));
+        let mut child: Child = command
+            .env("PORT", port.to_string())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let logs: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let mut log_readers: Vec<JoinHandle<()>> = vec![];
+        if let Some(stdout) = child.stdout.take() {
+            log_readers.push(stream_lines(stdout, logs.clone(), log_file.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            log_readers.push(stream_lines(stderr, logs.clone(), log_file));
+        }
+
+        Ok(Self {
+            pid: child.id(),
+            child: Some(child),
+            port,
+            logs,
+            log_readers,
+        })
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.pid
+    }
+
+    // Polls the port until the server accepts connections, exits or runs out of time
+    pub async fn wait_until_ready(&mut self, timeout: Duration) -> Result<(), AgentError> {
+        let deadline: Instant = Instant::now() + timeout;
+        loop {
+            let exit_status: Option<ExitStatus> = match self.child.as_mut() {
+                Some(child) => child.try_wait()?,
+                None => {
+                    return Err(AgentError::Build(format!(
+                        "Server on port {} was already stopped",
+                        self.port
+                    )))
+                }
+            };
+            if let Some(status) = exit_status {
+                return Err(AgentError::Build(format!(
+                    "Server exited ({}) before listening on port {}",
+                    status, self.port
+                )));
+            }
+            if TcpStream::connect(("127.0.0.1", self.port)).await.is_ok() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(AgentError::Build(format!(
+                    "Server was not listening on port {} (set in the PORT environment variable) after {} seconds",
+                    self.port,
+                    timeout.as_secs_f32()
+                )));
+            }
+            time::sleep(PROBE_INTERVAL).await;
+        }
+    }
+
+    // Everything the server printed to stdout and stderr so far
+    pub fn logs(&self) -> String {
+        match self.logs.lock() {
+            Ok(logs) => logs.join("\n"),
+            Err(_) => String::new(),
+        }
+    }
+
+    // Kills the server and waits for it and its last log lines off the runtime's worker threads
+    pub async fn stop(&mut self) -> Result<(), AgentError> {
+        let child: Child = match self.child.take() {
+            Some(child) => child,
+            None => return Ok(()),
+        };
+        let log_readers: Vec<JoinHandle<()>> = std::mem::take(&mut self.log_readers);
+        task::spawn_blocking(move || shut_down(child, log_readers)).await??;
+        Ok(())
+    }
+}
+
+impl Drop for ServerProcess {
+    // Kills right away, reaping the process is left to a blocking thread when inside the runtime
+    fn drop(&mut self) {
+        let mut child: Child = match self.child.take() {
+            Some(child) => child,
+            None => return,
+        };
+        let _ = child.kill();
+        let log_readers: Vec<JoinHandle<()>> = std::mem::take(&mut self.log_readers);
+        match Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(move || shut_down(child, log_readers));
+            }
+            Err(_) => {
+                let _ = shut_down(child, log_readers);
+            }
+        }
+    }
+}
+
+// Blocking: kills and reaps the server, then gives the readers a moment to pick up the last lines
+fn shut_down(mut child: Child, log_readers: Vec<JoinHandle<()>>) -> Result<(), AgentError> {
+    if child.try_wait()?.is_none() {
+        child.kill()?;
+    }
+    child.wait()?;
+
+    let deadline: Instant = Instant::now() + LOG_DRAIN_TIMEOUT;
+    while log_readers.iter().any(|reader| !reader.is_finished()) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    Ok(())
+}
+
+fn stream_lines<R: Read + Send + 'static>(
+    reader: R,
+    logs: Arc<Mutex<Vec<String>>>,
+    log_file: Arc<Mutex<File>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines().map_while(Result::ok) {
+            if let Ok(mut file) = log_file.lock() {
+                let _ = writeln!(file, "{}", line);
+            }
+            if let Ok(mut logs) = logs.lock() {
+                logs.push(line);
+                if logs.len() > LOG_LINES_KEPT {
+                    logs.remove(0);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn is_alive(pid: u32) -> bool {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(Stdio::null())
+            .status()
+            .unwrap()
+            .success()
+    }
+
+    fn shell(script: &str) -> Command {
+        let mut command: Command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+
+    #[tokio::test]
+    async fn tests_server_process_lifecycle() {
+        let port: u16 = free_port().unwrap();
+        let log_path = std::env::temp_dir().join(format!("auto_gpt_server_{}.log", port));
+        let mut server: ServerProcess = ServerProcess::start(
+            shell("echo \"starting on $PORT\"; echo boom >&2; exec sleep 30"),
+            port,
+            &log_path,
+        )
+        .unwrap();
+
+        // Nothing listens on the port yet
+        assert!(server
+            .wait_until_ready(Duration::from_millis(300))
+            .await
+            .is_err());
+        let _listener: TcpListener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        server
+            .wait_until_ready(Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        // Dropping the supervisor kills the server and reaps it off the runtime
+        let pid: u32 = server.id();
+        drop(server);
+        let deadline: Instant = Instant::now() + Duration::from_secs(5);
+        while is_alive(pid) && Instant::now() < deadline {
+            time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!is_alive(pid));
+
+        let log: String = fs::read_to_string(&log_path).unwrap();
+        assert!(log.contains(&format!("starting on {}", port)));
+        assert!(log.contains("boom"));
+
+        let mut crashed: ServerProcess = ServerProcess::start(
+            shell("echo panicked >&2; exit 3"),
+            free_port().unwrap(),
+            &log_path,
+        )
+        .unwrap();
+        let err: AgentError = crashed
+            .wait_until_ready(Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("exited"));
+        crashed.stop().await.unwrap();
+        assert_eq!(crashed.logs(), "panicked");
+
+        // A log file that cannot be created stops the start before anything is spawned
+        let marker = std::env::temp_dir().join(format!("auto_gpt_server_{}.started", port));
+        let missing_dir = std::env::temp_dir().join(format!("auto_gpt_missing_{}", port));
+        assert!(ServerProcess::start(
+            shell(&format!("touch {}; exec sleep 30", marker.display())),
+            free_port().unwrap(),
+            &missing_dir.join("server.log"),
+        )
+        .is_err());
+        time::sleep(Duration::from_millis(200)).await;
+        assert!(!marker.exists());
+
+        let _ = fs::remove_file(&log_path);
+    }
+}
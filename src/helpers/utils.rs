@@ -1,16 +1,27 @@
 use std::fs;
+use std::path::PathBuf;
 
-use super::command_line::PrintCommand;
-use crate::{apis::call_request::call_gpt, models::general::llm::Message};
+use super::{
+    command_line::PrintCommand, json_decode::decode_llm_json, openapi::openapi_document,
+    workspace::Workspace,
+};
+use crate::{
+    apis::{
+        llm_provider::LlmProvider,
+        usage::{estimate_call, record_usage, reserve_spend, SpendReservation},
+    },
+    models::{
+        agent_basic::{basic_agent::BasicAgent, basic_traits::BasicTraits},
+        agents::agent_trait::{ApiSchemaFile, RouteObject, API_SCHEMA_VERSION},
+        general::{
+            errors::AgentError,
+            llm::{LlmResponse, Message},
+        },
+    },
+};
 use reqwest::Client;
 use serde::de::DeserializeOwned;
 
-pub const WEB_SERVER_PROJECT_PATH: &str = "src/templates/web_server";
-pub const WEB_APP_PROJECT_PATH: &str = "src/templates/web_app";
-pub const API_SCHEMA_JSON: &str = "src/templates/web_server/schema/api_schema.json";
-const CODE_TEMPLATE_PATH: &str = "src/templates/web_server/src/code_template.rs";
-const EXEC_MAIN_PATH: &str = "src/templates/web_server/src/main.rs";
-
 // Extend AI function to encourage certain specific output
 pub fn extend_ai_function(ai_func: fn(&str) -> &'static str, arguments: &str) -> Message {
     let ai_func_string = ai_func(arguments);
@@ -24,95 +35,242 @@ pub fn extend_ai_function(ai_func: fn(&str) -> &'static str, arguments: &str) ->
         ai_func_string, arguments
     );
 
-    return Message {
+    Message {
         role: "system".to_string(),
         content,
-    };
+    }
 }
 
+// Bounded number of times the model is asked to fix a response that would not decode
+const MAX_DECODE_REPAIR_ATTEMPTS: u8 = 2;
+
 // Performs call to LLM GPT
 pub async fn ai_task_request(
+    llm: &dyn LlmProvider,
     msg_context: String,
     agent_position: &str,
     agent_operation: &str,
     function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> String {
+) -> Result<String, AgentError> {
     let extended_message = extend_ai_function(function_pass, &msg_context);
 
     PrintCommand::AICall.print_agent_message(agent_position, agent_operation);
 
-    let llm_response: Result<String, Box<dyn std::error::Error + Send>> =
-        call_gpt(vec![extended_message.clone()]).await;
-
-    return match llm_response {
-        Ok(result) => result,
-        Err(_) => call_gpt(vec![extended_message.clone()])
-            .await
-            .expect("Failed twice to call Open AI"),
-    };
+    return call_and_record(llm, &[extended_message], agent_position, agent_operation).await;
 }
 
-// Performs call to LLM GPT -- Decoded -> Parses JSON string to parametrized structure
-pub async fn ai_task_request_decoded<T: DeserializeOwned>(
+// Performs call to LLM GPT for an agent -- sends `history` ahead of the prompt and records the exchange in the agent's memory
+pub async fn agent_task_request(
+    llm: &dyn LlmProvider,
+    agent: &mut BasicAgent,
+    history: &[Message],
     msg_context: String,
-    agent_position: &str,
     agent_operation: &str,
     function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> T {
+) -> Result<String, AgentError> {
+    let extended_message: Message = extend_ai_function(function_pass, &msg_context);
+
+    PrintCommand::AICall.print_agent_message(&agent.position, agent_operation);
+
+    let mut messages: Vec<Message> = history.to_vec();
+    messages.push(extended_message.clone());
     let llm_response: String =
-        ai_task_request(msg_context, agent_position, agent_operation, function_pass).await;
+        call_and_record(llm, &messages, &agent.position, agent_operation).await?;
+
+    agent.remember(extended_message, &llm_response);
+    Ok(llm_response)
+}
+
+// Performs call to LLM GPT for an agent -- Decoded -> Parses JSON string to parametrized structure
+// Responses that still fail after cleanup are sent back to the model along with the serde error
+pub async fn agent_task_request_decoded<T: DeserializeOwned>(
+    llm: &dyn LlmProvider,
+    agent: &mut BasicAgent,
+    msg_context: String,
+    agent_operation: &str,
+    function_pass: for<'a> fn(&'a str) -> &'static str,
+) -> Result<T, AgentError> {
+    let extended_message: Message = extend_ai_function(function_pass, &msg_context);
+    let agent_position: &str = &agent.position;
+
+    PrintCommand::AICall.print_agent_message(agent_position, agent_operation);
+
+    let mut messages: Vec<Message> = vec![extended_message.clone()];
+    let mut llm_response: String =
+        call_and_record(llm, &messages, agent_position, agent_operation).await?;
+    let mut repair_attempts: u8 = 0;
+
+    loop {
+        let decode_err: serde_json::Error = match decode_llm_json::<T>(&llm_response) {
+            Ok(decoded) => {
+                agent.remember(extended_message, &llm_response);
+                return Ok(decoded);
+            }
+            Err(e) => e,
+        };
 
-    let decoded_response: T = serde_json::from_str(llm_response.as_str())
-        .expect("Failed to decode AI response from serde_json");
+        if repair_attempts >= MAX_DECODE_REPAIR_ATTEMPTS {
+            return Err(AgentError::Decode(format!(
+                "Failed to decode AI response for {} after {} repair attempts: {} -- response: {}",
+                agent_operation, repair_attempts, decode_err, llm_response
+            )));
+        }
+        repair_attempts += 1;
 
-    return decoded_response;
+        PrintCommand::Issue.print_agent_message(
+            agent_position,
+            format!(
+                "Response could not be decoded ({}), asking for a fix ({}/{})",
+                decode_err, repair_attempts, MAX_DECODE_REPAIR_ATTEMPTS
+            )
+            .as_str(),
+        );
+
+        messages.push(Message {
+            role: "assistant".to_string(),
+            content: llm_response,
+        });
+        messages.push(decode_repair_message::<T>(&decode_err));
+        llm_response = call_and_record(llm, &messages, agent_position, agent_operation).await?;
+    }
+}
+
+// Calls the provider and adds the tokens spent to the usage ledger, refusing once the budget is spent.
+// The reservation covers the first attempt, RetryingProvider reserves and counts any retries
+async fn call_and_record(
+    llm: &dyn LlmProvider,
+    messages: &[Message],
+    agent_position: &str,
+    agent_operation: &str,
+) -> Result<String, AgentError> {
+    let reservation: SpendReservation =
+        reserve_spend(estimate_call(llm.model().as_deref(), messages))?;
+    let llm_response: LlmResponse = llm.call(messages.to_vec()).await?;
+    record_usage(reservation, agent_position, agent_operation, &llm_response);
+    Ok(llm_response.content)
+}
+
+// Follow up prompt asking the model to resend its previous answer as valid JSON
+fn decode_repair_message<T>(decode_err: &serde_json::Error) -> Message {
+    let content: String = format!(
+        "Your previous output could not be parsed as JSON: {}.
+    EXPECTED SCHEMA: a JSON value deserializable into the Rust type `{}`, exactly as described in the FUNCTION output above.
+    Print ONLY the corrected JSON. No markdown code fences. No comments. No commentary.",
+        decode_err,
+        std::any::type_name::<T>()
+    );
+
+    Message {
+        role: "user".to_string(),
+        content,
+    }
 }
 
 pub async fn check_status_code(client: &Client, url: &str) -> Result<u16, reqwest::Error> {
     let response: reqwest::Response = client.get(url).send().await?;
-    return Ok(response.status().as_u16());
+    Ok(response.status().as_u16())
 }
 
-pub fn read_code_template_content() -> String {
-    let path: String = String::from(CODE_TEMPLATE_PATH);
-    return fs::read_to_string(path).expect("Failed to read code template");
+pub fn read_code_template_content(workspace: &Workspace) -> Result<String, AgentError> {
+    Ok(fs::read_to_string(workspace.code_template_path())?)
 }
 
-pub fn read_executable_main_content() -> String {
-    let path: String = String::from(EXEC_MAIN_PATH);
-    return fs::read_to_string(path).expect("Failed to read code template");
+pub fn read_executable_main_content(workspace: &Workspace) -> Result<String, AgentError> {
+    Ok(fs::read_to_string(workspace.exec_main_path())?)
 }
 
-pub fn save_backend_code(content: &str) -> () {
-    let path: String = String::from(EXEC_MAIN_PATH);
-    fs::write(path, content).expect("Failed to write main.rs file");
+pub fn save_backend_code(workspace: &Workspace, content: &str) -> Result<(), AgentError> {
+    Ok(fs::write(workspace.exec_main_path(), content)?)
 }
 
-pub fn save_api_endpoints(api_endpoints: &String) {
-    let path: String = String::from(API_SCHEMA_JSON);
-    fs::write(path, api_endpoints).expect("Failed to write API endpoints to file");
+pub fn save_api_endpoints(
+    workspace: &Workspace,
+    api_endpoints: &[RouteObject],
+) -> Result<(), AgentError> {
+    let schema_file: ApiSchemaFile = ApiSchemaFile {
+        schema_version: API_SCHEMA_VERSION,
+        endpoints: api_endpoints.to_vec(),
+    };
+    let contents: String = serde_json::to_string_pretty(&schema_file)?;
+    Ok(fs::write(workspace.api_schema_path(), contents)?)
+}
+
+pub fn save_openapi_document(
+    workspace: &Workspace,
+    project_description: &str,
+    server_url: &str,
+    api_endpoints: &[RouteObject],
+) -> Result<(), AgentError> {
+    let document: serde_json::Value =
+        openapi_document(project_description, server_url, api_endpoints);
+    let contents: String = serde_json::to_string_pretty(&document)?;
+    Ok(fs::write(workspace.openapi_path(), contents)?)
+}
+
+// Agents pass the schema in memory through the fact sheet, the file is for people and tooling
+pub fn read_api_endpoints(workspace: &Workspace) -> Result<Vec<RouteObject>, AgentError> {
+    let path: PathBuf = workspace.api_schema_path();
+    let contents: String = fs::read_to_string(&path)?;
+    let schema_file: ApiSchemaFile = serde_json::from_str(&contents).map_err(|e| {
+        AgentError::Decode(format!("Invalid API schema file {}: {}", path.display(), e))
+    })?;
+
+    if schema_file.schema_version != API_SCHEMA_VERSION {
+        return Err(AgentError::Decode(format!(
+            "API schema file {} is version {}, expected version {}",
+            path.display(),
+            schema_file.schema_version,
+            API_SCHEMA_VERSION
+        )));
+    }
+    Ok(schema_file.endpoints)
 }
 
-pub fn save_frontend_code(frontend_path: &String, contents: &String) {
-    let path: String = format!("{}{}", WEB_APP_PROJECT_PATH, frontend_path);
-    fs::write(path, contents).expect("Something went wrong saving the file");
+pub fn save_frontend_code(
+    workspace: &Workspace,
+    frontend_path: &String,
+    contents: &String,
+) -> Result<(), AgentError> {
+    let path: PathBuf = PathBuf::from(format!(
+        "{}{}",
+        workspace.web_app_path().display(),
+        frontend_path
+    ));
+    // Page components go into new files, i.e. src/components/pages/ProgressDashboard.tsx
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::write(path, contents)?)
 }
 
-pub fn read_frontend_code_contents(frontend_path: &String) -> String {
-    let path: String = format!("{}{}", WEB_APP_PROJECT_PATH, frontend_path);
-    fs::read_to_string(path).expect("Something went wrong reading the file")
+pub fn read_frontend_code_contents(
+    workspace: &Workspace,
+    frontend_path: &String,
+) -> Result<String, AgentError> {
+    let path: String = format!("{}{}", workspace.web_app_path().display(), frontend_path);
+    Ok(fs::read_to_string(path)?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ai_functions::managing::convert_user_input_to_goal;
+    use crate::apis::replay_provider::test_provider;
+    use crate::apis::scripted_provider::ScriptedProvider;
+    use crate::helpers::workspace::test_workspace;
+
+    fn scripted_provider(responses: &[&str]) -> ScriptedProvider {
+        let script: Vec<(&str, &str)> = responses
+            .iter()
+            .map(|response| ("convert_user_input_to_goal", *response))
+            .collect();
+        ScriptedProvider::new(&script)
+    }
 
     #[test]
     fn tests_extending_ai_function() {
         let extended_message: Message =
             extend_ai_function(convert_user_input_to_goal, "dummy variable");
-        dbg!(&extended_message);
         assert_eq!(extended_message.role, "system".to_string());
     }
 
@@ -121,14 +279,148 @@ mod tests {
         let ai_func_param: String =
             "Build me a webserver for making stock price API requests.".to_string();
         let res: String = ai_task_request(
+            test_provider().as_ref(),
             ai_func_param,
             "Managing Agent",
             "Defining user requirements",
             convert_user_input_to_goal,
         )
-        .await;
+        .await
+        .expect("Failed to call LLM");
 
-        dbg!(&res);
         assert!(res.len() > 20);
     }
+
+    #[tokio::test]
+    async fn tests_decoded_request_reprompts_with_decode_error() {
+        let llm: ScriptedProvider = scripted_provider(&[
+            "I cannot decide on the urls",
+            "```json\n[\"https://a.com\",]\n```",
+        ]);
+
+        let mut agent: BasicAgent =
+            BasicAgent::new("Decodes urls".to_string(), "Managing Agent".to_string());
+        let res: Vec<String> = agent_task_request_decoded(
+            &llm,
+            &mut agent,
+            "dummy variable".to_string(),
+            "Decoding urls",
+            convert_user_input_to_goal,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(res, vec!["https://a.com".to_string()]);
+        // Memory keeps the prompt and the answer that decoded, not the repair turns
+        assert_eq!(agent.memory.len(), 2);
+        assert_eq!(
+            agent.memory[1].content,
+            "```json\n[\"https://a.com\",]\n```"
+        );
+        let last_messages: Vec<Message> = llm.calls().pop().unwrap();
+        assert_eq!(last_messages.len(), 3);
+        assert_eq!(last_messages[1].content, "I cannot decide on the urls");
+        assert!(last_messages[2]
+            .content
+            .contains("Vec<alloc::string::String>"));
+    }
+
+    #[tokio::test]
+    async fn tests_decoded_request_gives_up_after_bounded_attempts() {
+        let llm: ScriptedProvider = scripted_provider(&["nope", "still nope", "never"]);
+
+        let mut agent: BasicAgent =
+            BasicAgent::new("Decodes urls".to_string(), "Managing Agent".to_string());
+        let res: Result<Vec<String>, AgentError> = agent_task_request_decoded(
+            &llm,
+            &mut agent,
+            "dummy variable".to_string(),
+            "Decoding urls",
+            convert_user_input_to_goal,
+        )
+        .await;
+
+        assert!(matches!(res, Err(AgentError::Decode(_))));
+        assert_eq!(llm.calls().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn tests_agent_requests_are_remembered() {
+        let llm: ScriptedProvider = scripted_provider(&["first fix", "second fix"]);
+        let mut agent: BasicAgent =
+            BasicAgent::new("Fixes code".to_string(), "Backend Developer".to_string());
+
+        agent_task_request(
+            &llm,
+            &mut agent,
+            &[],
+            "broken code".to_string(),
+            "Fixing code",
+            convert_user_input_to_goal,
+        )
+        .await
+        .unwrap();
+        assert_eq!(agent.memory.len(), 2);
+        assert_eq!(agent.memory[1].role, "assistant");
+
+        // History goes ahead of the new prompt
+        let history: Vec<Message> = agent.memory.clone();
+        agent_task_request(
+            &llm,
+            &mut agent,
+            &history,
+            "still broken code".to_string(),
+            "Fixing code",
+            convert_user_input_to_goal,
+        )
+        .await
+        .unwrap();
+        let last_messages: Vec<Message> = llm.calls().pop().unwrap();
+        assert_eq!(last_messages.len(), 3);
+        assert_eq!(last_messages[1].content, "first fix");
+        assert!(last_messages[2].content.contains("still broken code"));
+        assert_eq!(agent.memory.len(), 4);
+        assert_eq!(agent.memory[3].content, "second fix");
+    }
+
+    #[test]
+    fn tests_api_schema_file_is_versioned() {
+        let (_output_dir, workspace): (tempfile::TempDir, Workspace) = test_workspace();
+        let endpoints: Vec<RouteObject> = vec![RouteObject {
+            route: "/cats/{id}".to_string(),
+            is_route_dynamic: true,
+            method: "get".to_string(),
+            request_body: serde_json::Value::Null,
+            response: serde_json::json!({ "id": "number", "url": "string" }),
+        }];
+
+        save_api_endpoints(&workspace, &endpoints).unwrap();
+        assert_eq!(read_api_endpoints(&workspace).unwrap(), endpoints);
+
+        // Unversioned files from before the schema was versioned are rejected, not misread
+        fs::write(
+            workspace.api_schema_path(),
+            serde_json::to_string(&endpoints).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            read_api_endpoints(&workspace),
+            Err(AgentError::Decode(_))
+        ));
+
+        let stale: ApiSchemaFile = ApiSchemaFile {
+            schema_version: API_SCHEMA_VERSION + 1,
+            endpoints,
+        };
+        fs::write(
+            workspace.api_schema_path(),
+            serde_json::to_string(&stale).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            read_api_endpoints(&workspace),
+            Err(AgentError::Decode(_))
+        ));
+        let _ = fs::remove_dir_all(&workspace.root);
+    }
 }
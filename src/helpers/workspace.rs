@@ -0,0 +1,329 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::utils::{save_api_endpoints, save_openapi_document};
+use crate::models::{agents::agent_trait::FactSheet, general::errors::AgentError};
+
+// Pristine templates shipped with the tool -- runs never write here. Only valid where the crate was
+// built, so installed binaries are pointed at a copy with --templates-dir or AUTO_GPT_TEMPLATES_DIR
+const DEFAULT_TEMPLATES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/templates");
+const WEB_SERVER_DIR: &str = "web_server";
+const WEB_APP_DIR: &str = "web_app";
+
+// Build output and installed packages are recreated inside each workspace
+const SKIPPED_TEMPLATE_ENTRIES: [&str; 3] = ["target", "node_modules", "dist"];
+// Shared by the web servers of every run in an output directory, next to the runs
+const SHARED_TARGET_DIR: &str = "target";
+// The crate's tests share one across their temporary output directories
+#[cfg(test)]
+const TEST_TARGET_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/test_web_servers");
+
+// Keeps workspaces created by the same process in the same millisecond apart
+static WORKSPACE_SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+// Per-run copy of the web_server and web_app templates that agents generate code into
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Workspace {
+    pub root: PathBuf,
+    // None builds into the target directory shared by the runs next to this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_target_dir: Option<PathBuf>,
+}
+
+impl Workspace {
+    // Copies the templates into a fresh `<output_dir>/runs/run-<timestamp>-<pid>-<n>` directory
+    pub fn create(output_dir: &Path, templates_dir: &Path) -> Result<Self, AgentError> {
+        if !templates_dir.join(WEB_SERVER_DIR).is_dir() || !templates_dir.join(WEB_APP_DIR).is_dir()
+        {
+            return Err(AgentError::Config(format!(
+                "No {} and {} templates in {} (pass --templates-dir or set AUTO_GPT_TEMPLATES_DIR)",
+                WEB_SERVER_DIR,
+                WEB_APP_DIR,
+                templates_dir.display()
+            )));
+        }
+
+        let timestamp: u128 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or(0);
+        let sequence: u32 = WORKSPACE_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        let root: PathBuf = output_dir.join("runs").join(format!(
+            "run-{}-{}-{}",
+            timestamp,
+            process::id(),
+            sequence
+        ));
+
+        // Claiming the directory up front means two runs can never share it
+        fs::create_dir_all(output_dir.join("runs"))?;
+        if let Err(e) = fs::create_dir(&root) {
+            return Err(AgentError::Config(format!(
+                "Unable to create workspace {}: {}",
+                root.display(),
+                e
+            )));
+        }
+
+        copy_template_dir(
+            &templates_dir.join(WEB_SERVER_DIR),
+            &root.join(WEB_SERVER_DIR),
+        )?;
+        copy_template_dir(&templates_dir.join(WEB_APP_DIR), &root.join(WEB_APP_DIR))?;
+
+        let workspace: Self = Self::open(root);
+        let run_name: String = workspace.run_name().replace('-', "_");
+        workspace.name_server_package(&format!("web_server_{}", run_name))?;
+        Ok(workspace)
+    }
+
+    // Workspace of an earlier run (i.e. when resuming from a checkpoint)
+    pub fn open(root: PathBuf) -> Self {
+        Self {
+            root,
+            shared_target_dir: None,
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.web_server_path().is_dir() && self.web_app_path().is_dir()
+    }
+
+    pub fn web_server_path(&self) -> PathBuf {
+        self.root.join(WEB_SERVER_DIR)
+    }
+
+    pub fn web_app_path(&self) -> PathBuf {
+        self.root.join(WEB_APP_DIR)
+    }
+
+    pub fn api_schema_path(&self) -> PathBuf {
+        self.web_server_path().join("schema/api_schema.json")
+    }
+
+    pub fn openapi_path(&self) -> PathBuf {
+        self.web_server_path().join("schema/openapi.json")
+    }
+
+    pub fn code_template_path(&self) -> PathBuf {
+        self.web_server_path().join("src/code_template.rs")
+    }
+
+    pub fn exec_main_path(&self) -> PathBuf {
+        self.web_server_path().join("src/main.rs")
+    }
+
+    // Written after each agent runs, kept with the run it belongs to so runs never share one
+    pub fn checkpoint_path(&self) -> PathBuf {
+        self.root.join("checkpoint.json")
+    }
+
+    // Where the fact sheet is saved when the spend budget runs out mid-run
+    pub fn fact_sheet_path(&self) -> PathBuf {
+        self.root.join("fact_sheet.json")
+    }
+
+    pub fn server_log_path(&self) -> PathBuf {
+        self.web_server_path().join("server.log")
+    }
+
+    // Web servers are all built into one target directory, so actix and the other dependencies are
+    // compiled once rather than for every run
+    pub fn cargo_target_dir(&self) -> PathBuf {
+        match &self.shared_target_dir {
+            Some(shared_target_dir) => shared_target_dir.clone(),
+            None => self
+                .root
+                .parent()
+                .unwrap_or(&self.root)
+                .join(SHARED_TARGET_DIR),
+        }
+    }
+
+    // Built by `cargo build` into the shared target directory and named after its package
+    pub fn server_binary_path(&self) -> Result<PathBuf, AgentError> {
+        let manifest: String = fs::read_to_string(self.web_server_path().join("Cargo.toml"))?;
+        let package_name: Option<String> = manifest.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            match key.trim() == "name" {
+                true => Some(value.trim().trim_matches('"').to_string()),
+                false => None,
+            }
+        });
+        match package_name {
+            Some(name) => Ok(self.cargo_target_dir().join("debug").join(name)),
+            None => Err(AgentError::Config(
+                "Web server Cargo.toml has no package name".to_string(),
+            )),
+        }
+    }
+
+    // i.e. "run-1718000000000-4242-0"
+    pub fn run_name(&self) -> String {
+        self.root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    // The package is named after the run, so servers built into the shared target directory at
+    // the same time never overwrite each other's binary
+    fn name_server_package(&self, package_name: &str) -> Result<(), AgentError> {
+        let manifest_path: PathBuf = self.web_server_path().join("Cargo.toml");
+        let manifest: String = fs::read_to_string(&manifest_path)?;
+        let mut is_named: bool = false;
+        let lines: Vec<String> = manifest
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((key, _)) if key.trim() == "name" && !is_named => {
+                    is_named = true;
+                    format!("name = \"{}\"", package_name)
+                }
+                _ => line.to_string(),
+            })
+            .collect();
+        Ok(fs::write(manifest_path, lines.join("\n") + "\n")?)
+    }
+
+    // Puts backend output recorded in a fact sheet back on disk, so later agents can build on it
+    pub fn write_fact_sheet_outputs(
+        &self,
+        fact_sheet: &FactSheet,
+        server_url: &str,
+    ) -> Result<(), AgentError> {
+        if let Some(backend_code) = &fact_sheet.backend_code {
+            fs::write(self.exec_main_path(), backend_code)?;
+        }
+        if let Some(api_endpoint_schema) = &fact_sheet.api_endpoint_schema {
+            save_api_endpoints(self, api_endpoint_schema)?;
+            save_openapi_document(
+                self,
+                &fact_sheet.project_description,
+                server_url,
+                api_endpoint_schema,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// AUTO_GPT_TEMPLATES_DIR, otherwise the templates in the crate the binary was built from
+pub fn templates_dir_from_env() -> PathBuf {
+    match env::var("AUTO_GPT_TEMPLATES_DIR") {
+        Ok(templates_dir) if !templates_dir.trim().is_empty() => PathBuf::from(templates_dir),
+        _ => PathBuf::from(DEFAULT_TEMPLATES_PATH),
+    }
+}
+
+fn copy_template_dir(source: &Path, destination: &Path) -> Result<(), AgentError> {
+    fs::create_dir_all(destination)?;
+
+    for entry in fs::read_dir(source)? {
+        let entry: fs::DirEntry = entry?;
+        let file_name = entry.file_name();
+        if SKIPPED_TEMPLATE_ENTRIES
+            .iter()
+            .any(|skipped| file_name.as_os_str() == *skipped)
+        {
+            continue;
+        }
+
+        let target: PathBuf = destination.join(&file_name);
+        if entry.file_type()?.is_dir() {
+            copy_template_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+// Fresh workspace for the crate's tests, so they never write into the templates. It is deleted
+// along with the returned directory, which has to be kept for as long as the workspace is used
+#[cfg(test)]
+pub fn test_workspace() -> (tempfile::TempDir, Workspace) {
+    let output_dir: tempfile::TempDir =
+        tempfile::tempdir().expect("Unable to create test output directory");
+    let mut workspace: Workspace =
+        Workspace::create(output_dir.path(), Path::new(DEFAULT_TEMPLATES_PATH))
+            .expect("Unable to create test workspace");
+    workspace.shared_target_dir = Some(PathBuf::from(TEST_TARGET_PATH));
+    // Named after the workspace's place in the test run rather than the time, so the next
+    // `cargo test` rebuilds the same binaries instead of adding new ones
+    let sequence: String = workspace
+        .run_name()
+        .rsplit('-')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    workspace
+        .name_server_package(&format!("web_server_test_{}", sequence))
+        .expect("Unable to name test web server package");
+    (output_dir, workspace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_workspaces_are_isolated_copies() {
+        let (first_dir, first): (tempfile::TempDir, Workspace) = test_workspace();
+        let (_second_dir, second): (tempfile::TempDir, Workspace) = test_workspace();
+
+        assert_ne!(first.root, second.root);
+        assert_ne!(first.checkpoint_path(), second.checkpoint_path());
+        assert_ne!(first.fact_sheet_path(), second.fact_sheet_path());
+        assert!(first.exists() && second.exists());
+        assert!(first.code_template_path().is_file());
+
+        // Both servers build into the same target directory, each under its own binary name
+        let first_binary: PathBuf = first.server_binary_path().unwrap();
+        let second_binary: PathBuf = second.server_binary_path().unwrap();
+        assert_eq!(first_binary.parent(), second_binary.parent());
+        assert_ne!(first_binary, second_binary);
+        assert!(first_binary.starts_with(TEST_TARGET_PATH));
+
+        // Outside the tests the package is named after the run
+        let run_output_dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let run: Workspace =
+            Workspace::create(run_output_dir.path(), Path::new(DEFAULT_TEMPLATES_PATH)).unwrap();
+        assert_eq!(
+            run.server_binary_path().unwrap(),
+            run_output_dir
+                .path()
+                .join("runs/target/debug")
+                .join(format!("web_server_{}", run.run_name().replace('-', "_")))
+        );
+        assert_eq!(
+            Workspace::open(PathBuf::from("out/runs/run-1")).cargo_target_dir(),
+            PathBuf::from("out/runs/target")
+        );
+
+        // Writing generated code into one run leaves the other run and the templates alone
+        fs::write(first.exec_main_path(), "fn main() {}").unwrap();
+        let pristine: String =
+            fs::read_to_string(Path::new(DEFAULT_TEMPLATES_PATH).join("web_server/src/main.rs"))
+                .unwrap();
+        assert_eq!(
+            fs::read_to_string(second.exec_main_path()).unwrap(),
+            pristine
+        );
+
+        // Nothing is left behind once the test is done with a workspace
+        let first_root: PathBuf = first.root.clone();
+        drop(first_dir);
+        assert!(!first_root.exists());
+
+        let missing: tempfile::TempDir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            Workspace::create(missing.path(), missing.path()),
+            Err(AgentError::Config(_))
+        ));
+    }
+}
@@ -11,16 +11,217 @@ mod apis;
 mod helpers;
 mod models;
 
-use helpers::command_line::get_user_input;
-use models::agents_manager::managing_agent::ManagingAgent;
+use apis::llm_provider::{provider_from_env, LlmProvider};
+use apis::usage::{set_spend_budget, SpendBudget};
+use clap::Parser;
+use helpers::cli::{join_prompt, Cli, Command};
+use helpers::command_line::{
+    get_user_input, is_non_interactive, set_allow_unreviewed_code, set_non_interactive,
+    set_quiet, PrintCommand,
+};
+use helpers::workspace::Workspace;
+use models::agents::agent_trait::FactSheet;
+use models::agents_manager::{
+    checkpoint::{load_fact_sheet, Checkpoint},
+    managing_agent::{AgentSelection, ManagingAgent, RunConfig, RunReport},
+};
+use models::general::errors::AgentError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+
 #[tokio::main]
 async fn main() {
-    let user_req: String = get_user_input("What website are we building today?");
+    let cli: Cli = Cli::parse();
+    set_non_interactive(cli.non_interactive);
+    set_allow_unreviewed_code(cli.allow_unreviewed_code);
+    set_quiet(cli.is_quiet());
+    let command: Command = cli
+        .command
+        .clone()
+        .unwrap_or(Command::New { prompt: vec![] });
+
+    // Inspecting never spends anything, so no provider is needed
+    if let Command::Inspect { checkpoint } = &command {
+        match Checkpoint::load(checkpoint) {
+            Ok(checkpoint) => checkpoint.print(),
+            Err(e) => {
+                eprintln!(
+                    "Failed to load checkpoint {} -- {}",
+                    checkpoint.display(),
+                    e
+                );
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let budget: SpendBudget = match cli.spend_budget() {
+        Ok(budget) => budget,
+        Err(e) => {
+            eprintln!("Invalid spend budget -- {}", e);
+            process::exit(1);
+        }
+    };
+    set_spend_budget(budget);
+
+    if let Err(e) = fs::create_dir_all(&cli.output_dir) {
+        eprintln!(
+            "Failed to create output directory {} -- {}",
+            cli.output_dir.display(),
+            e
+        );
+        process::exit(1);
+    }
+
+    let llm: Arc<dyn LlmProvider> = match provider_from_env(&cli.provider_overrides()) {
+        Ok(llm) => llm,
+        Err(e) => {
+            eprintln!("Failed to set up LLM provider -- {}", e);
+            process::exit(1);
+        }
+    };
+    PrintCommand::AICall.print_agent_message(
+        "Project Manager",
+        format!("Using LLM provider {}", llm.name()).as_str(),
+    );
+    if !budget.is_unlimited() {
+        PrintCommand::AICall.print_agent_message(
+            "Project Manager",
+            format!("Spend budget: {}", budget).as_str(),
+        );
+    }
+
+    let templates_dir: PathBuf = cli.templates_dir();
+    let server_url: String = cli.server_url();
+    let mut managing_agent: ManagingAgent =
+        match create_managing_agent(command, cli.output_dir, &templates_dir, server_url, llm).await
+        {
+            Ok(managing_agent) => managing_agent,
+            Err(e) => {
+                eprintln!("Failed to start up Managing Agent -- {}", e);
+                process::exit(1);
+            }
+        };
+
+    PrintCommand::AICall.print_agent_message(
+        "Project Manager",
+        format!("Workspace: {}", managing_agent.workspace().root.display()).as_str(),
+    );
+
+    let report: RunReport = managing_agent.execute_project().await;
+    report.print();
 
-    let mut managing_agent: ManagingAgent = ManagingAgent::new(user_req)
-        .await
-        .expect("Failed to start up Managing Agent");
+    if !report.is_success() {
+        process::exit(1);
+    }
+}
+
+async fn create_managing_agent(
+    command: Command,
+    output_dir: PathBuf,
+    templates_dir: &Path,
+    server_url: String,
+    llm: Arc<dyn LlmProvider>,
+) -> Result<ManagingAgent, AgentError> {
+    match command {
+        Command::Resume { checkpoint } => {
+            let checkpoint: Checkpoint = Checkpoint::load(&checkpoint)?;
+
+            // Keep building in the workspace the checkpoint was written from, if it is still there
+            let workspace: Workspace = match checkpoint
+                .workspace
+                .clone()
+                .filter(|workspace| workspace.exists())
+            {
+                Some(workspace) => workspace,
+                None => fresh_workspace(
+                    &output_dir,
+                    templates_dir,
+                    &server_url,
+                    Some(&checkpoint.fact_sheet),
+                )?,
+            };
+
+            let config: RunConfig = RunConfig::new(AgentSelection::All, workspace, server_url);
+            Ok(ManagingAgent::from_checkpoint(checkpoint, llm, config))
+        }
+        Command::FrontendOnly { fact_sheet } => {
+            let fact_sheet: FactSheet = load_fact_sheet(&fact_sheet)?;
+            let workspace: Workspace =
+                fresh_workspace(&output_dir, templates_dir, &server_url, Some(&fact_sheet))?;
+            Ok(ManagingAgent::from_fact_sheet(
+                fact_sheet,
+                llm,
+                RunConfig::new(AgentSelection::FrontendOnly, workspace, server_url),
+            ))
+        }
+        Command::BackendOnly {
+            fact_sheet: Some(fact_sheet),
+            ..
+        } => {
+            let fact_sheet: FactSheet = load_fact_sheet(&fact_sheet)?;
+            let workspace: Workspace =
+                fresh_workspace(&output_dir, templates_dir, &server_url, Some(&fact_sheet))?;
+            Ok(ManagingAgent::from_fact_sheet(
+                fact_sheet,
+                llm,
+                RunConfig::new(AgentSelection::BackendOnly, workspace, server_url),
+            ))
+        }
+        Command::BackendOnly {
+            prompt,
+            fact_sheet: None,
+        } => {
+            let workspace: Workspace =
+                fresh_workspace(&output_dir, templates_dir, &server_url, None)?;
+            let config: RunConfig =
+                RunConfig::new(AgentSelection::BackendOnly, workspace, server_url);
+            ManagingAgent::new(user_request(&prompt)?, llm, config).await
+        }
+        Command::PlanOnly { prompt } => {
+            let workspace: Workspace =
+                fresh_workspace(&output_dir, templates_dir, &server_url, None)?;
+            let config: RunConfig = RunConfig::new(AgentSelection::PlanOnly, workspace, server_url);
+            ManagingAgent::new(user_request(&prompt)?, llm, config).await
+        }
+        Command::New { prompt } => {
+            let workspace: Workspace =
+                fresh_workspace(&output_dir, templates_dir, &server_url, None)?;
+            let config: RunConfig = RunConfig::new(AgentSelection::All, workspace, server_url);
+            ManagingAgent::new(user_request(&prompt)?, llm, config).await
+        }
+        Command::Inspect { .. } => Err(AgentError::Config(
+            "inspect does not start a run".to_string(),
+        )),
+    }
+}
+
+// Copies the templates for this run, restoring any backend output an earlier run already produced
+fn fresh_workspace(
+    output_dir: &Path,
+    templates_dir: &Path,
+    server_url: &str,
+    fact_sheet: Option<&FactSheet>,
+) -> Result<Workspace, AgentError> {
+    let workspace: Workspace = Workspace::create(output_dir, templates_dir)?;
+    if let Some(fact_sheet) = fact_sheet {
+        workspace.write_fact_sheet_outputs(fact_sheet, server_url)?;
+    }
+    Ok(workspace)
+}
 
-    managing_agent.execute_project().await;
-    dbg!(managing_agent);
+// The prompt from the command line, otherwise asked for on stdin
+fn user_request(prompt: &[String]) -> Result<String, AgentError> {
+    if let Some(prompt) = join_prompt(prompt) {
+        return Ok(prompt);
+    }
+    if is_non_interactive() {
+        return Err(AgentError::Config(
+            "a prompt is required in non-interactive mode".to_string(),
+        ));
+    }
+    get_user_input("What website are we building today?")
 }
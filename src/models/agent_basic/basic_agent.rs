@@ -1,7 +1,8 @@
 use crate::models::agent_basic::basic_traits::BasicTraits;
 use crate::models::general::llm::Message;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum AgentState {
     Discovery,
     Finished,
@@ -31,19 +32,24 @@ impl BasicTraits for BasicAgent {
         self.state = new_state;
     }
 
-    fn get_objective(&mut self) -> &String {
-        return &self.objective;
-    }
-
     fn get_position(&mut self) -> &String {
-        return &self.position;
+        &self.position
     }
 
     fn get_state(&mut self) -> &AgentState {
-        return &self.state;
+        &self.state
     }
 
     fn get_memory(&mut self) -> &Vec<Message> {
-        return &self.memory;
+        &self.memory
+    }
+
+    // Every prompt the agent sent, followed by the model's answer
+    fn remember(&mut self, prompt: Message, response: &str) {
+        self.memory.push(prompt);
+        self.memory.push(Message {
+            role: "assistant".to_string(),
+            content: response.to_string(),
+        });
     }
 }
@@ -1,29 +1,58 @@
 use async_trait::async_trait;
 use reqwest::Client;
+use std::fmt::Debug;
+use std::sync::Arc;
 use std::time::Duration;
 
 use crate::{
     ai_functions::architect::{print_project_scope, print_site_urls},
+    apis::llm_provider::LlmProvider,
     helpers::{
         command_line::PrintCommand,
-        utils::{ai_task_request_decoded, check_status_code},
+        utils::{agent_task_request_decoded, check_status_code},
     },
-    models::agent_basic::{
-        basic_agent::{AgentState, BasicAgent},
-        basic_traits::BasicTraits,
+    models::{
+        agent_basic::{
+            basic_agent::{AgentState, BasicAgent},
+            basic_traits::BasicTraits,
+        },
+        general::errors::AgentError,
     },
 };
 
-use super::agent_trait::{FactSheet, ProjectScope, SpecialFunctions};
+use super::agent_trait::{
+    AgentCheckpoint, FactSheet, FactSheetAccess, FactSheetField, ProjectScope, SpecialFunctions,
+};
+
+// Answers with the HTTP status of an external URL, tests swap in one that stays offline
+#[async_trait]
+pub trait UrlChecker: Debug + Send + Sync {
+    async fn status_code(&self, url: &str) -> Result<u16, AgentError>;
+}
+
+#[derive(Debug)]
+pub struct HttpUrlChecker {
+    timeout: Duration,
+}
+
+#[async_trait]
+impl UrlChecker for HttpUrlChecker {
+    async fn status_code(&self, url: &str) -> Result<u16, AgentError> {
+        let client: Client = Client::builder().timeout(self.timeout).build()?;
+        Ok(check_status_code(&client, url).await?)
+    }
+}
 
 #[derive(Debug)]
 pub struct AgentSolutionArchitect {
     attributes: BasicAgent,
+    llm: Arc<dyn LlmProvider>,
+    url_checker: Arc<dyn UrlChecker>,
 }
 
 impl AgentSolutionArchitect {
-    pub fn new() -> Self {
-        return Self {
+    pub fn new(llm: Arc<dyn LlmProvider>) -> Self {
+        Self {
             attributes: BasicAgent {
                 objective: "Gathers information and design solution for website development."
                     .to_string(),
@@ -31,67 +60,84 @@ impl AgentSolutionArchitect {
                 state: AgentState::Discovery,
                 memory: Vec::new(),
             },
-        };
+            llm,
+            url_checker: Arc::new(HttpUrlChecker {
+                timeout: Duration::from_secs(5),
+            }),
+        }
     }
 
-    pub async fn call_project_scope(&mut self, fact_sheet: &mut FactSheet) -> ProjectScope {
+    pub async fn call_project_scope(
+        &mut self,
+        fact_sheet: &mut FactSheet,
+    ) -> Result<ProjectScope, AgentError> {
         let msg_context: String = format!("{:?}", fact_sheet.project_description);
-        let ai_response: ProjectScope = ai_task_request_decoded::<ProjectScope>(
+        let ai_response: ProjectScope = agent_task_request_decoded::<ProjectScope>(
+            self.llm.as_ref(),
+            &mut self.attributes,
             msg_context,
-            &self.attributes.position,
             get_function_string!(print_project_scope),
             print_project_scope,
         )
-        .await;
-        fact_sheet.project_scope = Some(ai_response.clone());
+        .await?;
+        fact_sheet.project_scope = Some(ai_response);
 
         // /!\ WE MAKE SURE TO CHANGE STATE TO FINISHED, OTHERWISE WE RISK INFINITE LOOPS => INFINITE GPT API COSTS /!\
         self.attributes.update_state(AgentState::Finished);
-        return ai_response;
+        Ok(ai_response)
     }
 
     pub async fn call_determine_external_urls(
         &mut self,
         fact_sheet: &mut FactSheet,
         msg_context: String,
-    ) -> () {
-        let ai_response: Vec<String> = ai_task_request_decoded::<Vec<String>>(
+    ) -> Result<(), AgentError> {
+        let ai_response: Vec<String> = agent_task_request_decoded::<Vec<String>>(
+            self.llm.as_ref(),
+            &mut self.attributes,
             msg_context,
-            &self.attributes.position,
             get_function_string!(print_site_urls),
             print_site_urls,
         )
-        .await;
+        .await?;
         fact_sheet.external_urls = Some(ai_response);
         self.attributes.update_state(AgentState::UnitTesting);
+        Ok(())
     }
 
-    pub async fn handle_discovery_state(&mut self, fact_sheet: &mut FactSheet) -> () {
-        let project_scope: ProjectScope = self.call_project_scope(fact_sheet).await;
+    pub async fn handle_discovery_state(
+        &mut self,
+        fact_sheet: &mut FactSheet,
+    ) -> Result<(), AgentError> {
+        let project_scope: ProjectScope = self.call_project_scope(fact_sheet).await?;
         if project_scope.is_external_urls_required {
             self.call_determine_external_urls(fact_sheet, fact_sheet.project_description.clone())
-                .await;
+                .await?;
             self.attributes.update_state(AgentState::UnitTesting);
         }
+        Ok(())
     }
 
-    pub async fn handle_unit_testing_state(&mut self, fact_sheet: &mut FactSheet) -> () {
+    pub async fn handle_unit_testing_state(
+        &mut self,
+        fact_sheet: &mut FactSheet,
+    ) -> Result<(), AgentError> {
         let mut exclude_urls: Vec<String> = Vec::new();
-        let client: Client = Client::builder()
-            .timeout(Duration::from_secs(5))
-            .build()
-            .unwrap();
 
-        let urls: &Vec<String> = fact_sheet
-            .external_urls
-            .as_ref()
-            .expect("No URL object on fact sheet.");
+        let urls: &Vec<String> = match fact_sheet.external_urls.as_ref() {
+            Some(urls) => urls,
+            None => {
+                return Err(AgentError::MissingInput(
+                    "No URL object on fact sheet.".to_string(),
+                ))
+            }
+        };
 
         for url in urls {
             let endpoint_string: String = format!("Testing URL Endpoint: {}", url);
             PrintCommand::UnitTest.print_agent_message(&self.attributes.position, &endpoint_string);
 
-            match check_status_code(&client, url).await {
+            match self.url_checker.status_code(url).await {
                 Ok(status_code) => {
                     if status_code != 200 {
                         exclude_urls.push(url.clone());
@@ -101,13 +147,10 @@ impl AgentSolutionArchitect {
             }
         }
 
-        if exclude_urls.len() > 0 {
-            let new_urls: Vec<String> = fact_sheet
-                .external_urls
-                .as_ref()
-                .unwrap()
+        if !exclude_urls.is_empty() {
+            let new_urls: Vec<String> = urls
                 .iter()
-                .filter(|url| !exclude_urls.contains(&url))
+                .filter(|url| !exclude_urls.contains(url))
                 .cloned()
                 .collect();
             fact_sheet.external_urls = Some(new_urls);
@@ -115,24 +158,45 @@ impl AgentSolutionArchitect {
 
         // /!\ WE MAKE SURE TO CHANGE STATE TO FINISHED, OTHERWISE WE RISK INFINITE LOOPS => INFINITE GPT API COSTS /!\
         self.attributes.update_state(AgentState::Finished);
+        Ok(())
     }
 }
 
 #[async_trait]
 impl SpecialFunctions for AgentSolutionArchitect {
     fn get_agent_position(&mut self) -> String {
-        return self.attributes.get_position().clone();
+        self.attributes.get_position().clone()
     }
 
-    async fn execute(
-        &mut self,
-        fact_sheet: &mut FactSheet,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    fn fact_sheet_access(&self) -> FactSheetAccess {
+        FactSheetAccess {
+            reads: vec![FactSheetField::ProjectDescription],
+            optional_reads: vec![],
+            writes: vec![FactSheetField::ProjectScope, FactSheetField::ExternalUrls],
+        }
+    }
+
+    fn checkpoint(&self) -> AgentCheckpoint {
+        AgentCheckpoint {
+            position: self.attributes.position.clone(),
+            state: self.attributes.state,
+            design_build_sheet: None,
+        }
+    }
+
+    // Unfinished work is redone from the start
+    fn restore(&mut self, checkpoint: &AgentCheckpoint) {
+        if checkpoint.state == AgentState::Finished {
+            self.attributes.update_state(AgentState::Finished);
+        }
+    }
+
+    async fn execute(&mut self, fact_sheet: &mut FactSheet) -> Result<(), AgentError> {
         // /!\ WARNING BE CAREFUL OF INFINITE LOOPS => INFINITE GPT API COSTS /!\
         while self.attributes.get_state() != &AgentState::Finished {
-            match self.attributes.get_state() {
-                &AgentState::Discovery => self.handle_discovery_state(fact_sheet).await,
-                &AgentState::UnitTesting => self.handle_unit_testing_state(fact_sheet).await,
+            match *self.attributes.get_state() {
+                AgentState::Discovery => self.handle_discovery_state(fact_sheet).await?,
+                AgentState::UnitTesting => self.handle_unit_testing_state(fact_sheet).await?,
                 _ => self.attributes.update_state(AgentState::Finished),
             }
         }
@@ -143,10 +207,29 @@ impl SpecialFunctions for AgentSolutionArchitect {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::apis::replay_provider::test_provider;
+    use std::sync::Mutex;
+
+    // Fails the first URL it is asked about and passes the rest, without any network access
+    #[derive(Debug, Default)]
+    struct FirstUrlDown {
+        checked: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl UrlChecker for FirstUrlDown {
+        async fn status_code(&self, url: &str) -> Result<u16, AgentError> {
+            let mut checked = self.checked.lock().unwrap();
+            checked.push(url.to_string());
+            Ok(if checked.len() == 1 { 404 } else { 200 })
+        }
+    }
 
     #[tokio::test]
     async fn tests_solution_architect() {
-        let mut agent = AgentSolutionArchitect::new();
+        let url_checker: Arc<FirstUrlDown> = Arc::new(FirstUrlDown::default());
+        let mut agent = AgentSolutionArchitect::new(test_provider());
+        agent.url_checker = url_checker.clone();
         let mut fact_sheet = FactSheet {
             project_description: "Build a fullstack website with user login and logout that shows latest Forex prices".to_string(),
             project_scope: None,
@@ -160,8 +243,10 @@ mod tests {
             .await
             .expect("Unable to execute Solutions Architect Agent");
         assert!(fact_sheet.project_scope.is_some());
-        assert!(fact_sheet.external_urls.is_some());
 
-        dbg!(agent, fact_sheet);
+        // Only the URL that did not answer 200 is dropped
+        let checked: Vec<String> = url_checker.checked.lock().unwrap().clone();
+        assert!(!checked.is_empty());
+        assert_eq!(fact_sheet.external_urls, Some(checked[1..].to_vec()));
     }
 }
@@ -0,0 +1,498 @@
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::helpers::openapi::body_schema;
+use crate::models::agents::agent_trait::RouteObject;
+
+// Substituted into every `{id}`-style path segment
+const SAMPLE_PATH_VALUE: &str = "1";
+const SAMPLE_STRING: &str = "sample";
+
+// Creating routes run first, so later reads and deletes of `{id}` find the sample record
+const METHOD_ORDER: [&str; 8] = [
+    "post", "put", "patch", "get", "head", "options", "trace", "delete",
+];
+
+// Path segments (without '-' and '_') of routes that need real credentials
+const AUTH_ROUTE_SEGMENTS: [&str; 12] = [
+    "auth", "login", "logout", "signin", "signout", "signup", "register", "token", "session",
+    "password", "oauth", "me",
+];
+
+// What reqwest, hyper and curl say when a host cannot be reached
+const NETWORK_ERROR_MARKERS: [&str; 8] = [
+    "error sending request",
+    "dns error",
+    "failed to lookup address",
+    "tcp connect error",
+    "connection refused",
+    "network is unreachable",
+    "name or service not known",
+    "could not resolve host",
+];
+
+// Why a failing route does not count against the generated code
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum FailureCause {
+    // The sample request has no valid credentials
+    Auth,
+    // The handler calls an outside API, which is unreachable offline
+    ExternalApi,
+}
+
+impl FailureCause {
+    fn label(&self) -> &'static str {
+        match self {
+            FailureCause::Auth => "auth",
+            FailureCause::ExternalApi => "external API",
+        }
+    }
+}
+
+// Outcome of calling one route of the generated server
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EndpointResult {
+    pub method: String,
+    pub route: String,
+    pub url: String,
+    pub passed: bool,
+    pub status: Option<u16>,
+    pub cause: Option<FailureCause>,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct EndpointReport {
+    pub results: Vec<EndpointResult>,
+}
+
+impl EndpointReport {
+    // Every route fails for the same reason when the server never came up
+    pub fn unreachable(endpoints: &[RouteObject], reason: &str) -> Self {
+        let results: Vec<EndpointResult> = test_order(endpoints)
+            .into_iter()
+            .map(|endpoint| EndpointResult {
+                method: endpoint.method.to_lowercase(),
+                route: endpoint.route.clone(),
+                url: sample_path(&endpoint.route),
+                passed: false,
+                status: None,
+                cause: None,
+                detail: reason.to_string(),
+            })
+            .collect();
+        Self { results }
+    }
+
+    // Failures the generated code is to blame for
+    pub fn blocking_failures(&self) -> Vec<&EndpointResult> {
+        self.results
+            .iter()
+            .filter(|result| !result.passed && result.cause.is_none())
+            .collect()
+    }
+
+    // Failures of login routes and routes calling outside APIs, reported but not fixed
+    pub fn excused_failures(&self) -> Vec<&EndpointResult> {
+        self.results
+            .iter()
+            .filter(|result| !result.passed && result.cause.is_some())
+            .collect()
+    }
+
+    // A handler whose outside call failed often just panics or answers 500 with no body, so
+    // network errors in the server's output excuse those failures too
+    pub fn excuse_network_failures(&mut self, server_log: &str) {
+        if !has_network_error(server_log) {
+            return;
+        }
+        for result in self.results.iter_mut() {
+            let server_error: bool = match result.status {
+                Some(status) => status >= 500,
+                None => result.detail.starts_with("request failed"),
+            };
+            if !result.passed && result.cause.is_none() && server_error {
+                result.cause = Some(FailureCause::ExternalApi);
+            }
+        }
+    }
+
+    // One line per route, the form handed to the LLM when fixing the code
+    pub fn summary(&self) -> String {
+        self.results
+            .iter()
+            .map(|result| {
+                let outcome: String = match (result.passed, result.cause) {
+                    (true, _) => "PASS".to_string(),
+                    (false, None) => "FAIL".to_string(),
+                    (false, Some(cause)) => format!("FAIL ({}, not fatal)", cause.label()),
+                };
+                format!(
+                    "{} {} {}: {}",
+                    outcome,
+                    result.method.to_uppercase(),
+                    result.route,
+                    result.detail
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+// Calls every route with sample path values and request bodies and checks the response shape
+pub async fn run_endpoint_tests(
+    client: &Client,
+    base_url: &str,
+    endpoints: &[RouteObject],
+) -> EndpointReport {
+    let mut report: EndpointReport = EndpointReport::default();
+    for endpoint in test_order(endpoints) {
+        report
+            .results
+            .push(test_endpoint(client, base_url, endpoint).await);
+    }
+    report
+}
+
+fn test_order(endpoints: &[RouteObject]) -> Vec<&RouteObject> {
+    let mut ordered: Vec<&RouteObject> = endpoints.iter().collect();
+    ordered.sort_by_key(|endpoint| {
+        let method: String = endpoint.method.to_lowercase();
+        METHOD_ORDER
+            .iter()
+            .position(|known| *known == method)
+            .unwrap_or(METHOD_ORDER.len())
+    });
+    ordered
+}
+
+async fn test_endpoint(client: &Client, base_url: &str, endpoint: &RouteObject) -> EndpointResult {
+    let url: String = format!("{}{}", base_url, sample_path(&endpoint.route));
+    let mut result: EndpointResult = EndpointResult {
+        method: endpoint.method.to_lowercase(),
+        route: endpoint.route.clone(),
+        url: url.clone(),
+        passed: false,
+        status: None,
+        cause: None,
+        detail: String::new(),
+    };
+
+    let method: Method = match Method::from_bytes(endpoint.method.to_uppercase().as_bytes()) {
+        Ok(method) => method,
+        Err(_) => {
+            result.detail = format!("unknown HTTP method {:?}", endpoint.method);
+            return result;
+        }
+    };
+
+    let mut request: reqwest::RequestBuilder = client.request(method, &url);
+    if let Some(body) = body_schema(&endpoint.request_body).map(|schema| sample_value(&schema)) {
+        request = request.json(&body);
+    }
+
+    let response: reqwest::Response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            result.detail = format!("request failed: {}", e);
+            return result;
+        }
+    };
+    let status: u16 = response.status().as_u16();
+    let body: String = response.text().await.unwrap_or_default();
+    result.status = Some(status);
+
+    if !(200..300).contains(&status) {
+        result.cause = failure_cause(&endpoint.route, status, &body);
+        result.detail = format!("status {} {}", status, body.trim());
+        return result;
+    }
+
+    let (passed, detail): (bool, String) = match body_schema(&endpoint.response) {
+        Some(schema) => match serde_json::from_str::<Value>(&body) {
+            Ok(value) => match check_shape(&value, &schema, "response") {
+                Ok(()) => (true, format!("status {}, response matches schema", status)),
+                Err(mismatch) => (false, format!("{}, response: {}", mismatch, body.trim())),
+            },
+            Err(_) => (false, format!("response is not JSON: {}", body.trim())),
+        },
+        None => (true, format!("status {}", status)),
+    };
+    result.passed = passed;
+    result.detail = detail;
+    result
+}
+
+// Login routes reject the sample credentials and handlers calling outside APIs fail offline,
+// neither means the generated code is broken
+fn failure_cause(route: &str, status: u16, body: &str) -> Option<FailureCause> {
+    if has_network_error(body) {
+        return Some(FailureCause::ExternalApi);
+    }
+    if status == 401 || status == 403 || is_auth_route(route) {
+        return Some(FailureCause::Auth);
+    }
+    None
+}
+
+fn has_network_error(text: &str) -> bool {
+    let text: String = text.to_lowercase();
+    NETWORK_ERROR_MARKERS
+        .iter()
+        .any(|marker| text.contains(marker))
+}
+
+// "/auth/sign-in" and "/users/login" are, "/tokens_of_love/{id}" is not
+fn is_auth_route(route: &str) -> bool {
+    route.to_lowercase().split('/').any(|segment| {
+        let segment: String = segment.replace(['-', '_'], "");
+        AUTH_ROUTE_SEGMENTS.contains(&segment.as_str())
+    })
+}
+
+// "/task/{id}" => "/task/1"
+pub fn sample_path(route: &str) -> String {
+    route
+        .split('/')
+        .map(
+            |segment| match segment.starts_with('{') && segment.ends_with('}') {
+                true => SAMPLE_PATH_VALUE.to_string(),
+                false => segment.to_string(),
+            },
+        )
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+// Request body that satisfies a schema built by `body_schema`
+pub fn sample_value(schema: &Value) -> Value {
+    match schema["type"].as_str() {
+        Some("object") => {
+            let properties: Map<String, Value> = match schema["properties"].as_object() {
+                Some(properties) => properties
+                    .iter()
+                    .map(|(name, property)| (name.clone(), sample_value(property)))
+                    .collect(),
+                None => Map::new(),
+            };
+            Value::Object(properties)
+        }
+        Some("array") => match schema["items"].get("type") {
+            Some(_) => Value::Array(vec![sample_value(&schema["items"])]),
+            None => Value::Array(vec![]),
+        },
+        Some("integer") | Some("number") => Value::from(1),
+        Some("boolean") => Value::Bool(true),
+        Some("string") => match schema.get("example") {
+            Some(example) => example.clone(),
+            None => Value::String(SAMPLE_STRING.to_string()),
+        },
+        _ => Value::Null,
+    }
+}
+
+// Err describes the first place the value does not have the schema's shape
+pub fn check_shape(value: &Value, schema: &Value, at: &str) -> Result<(), String> {
+    let expected: &str = match schema["type"].as_str() {
+        Some(expected) => expected,
+        None => return Ok(()),
+    };
+    if value.is_null() && schema["nullable"] == Value::Bool(true) {
+        return Ok(());
+    }
+    // Unrecognised type names (i.e. "not_provided") only document an example
+    if expected == "string" && schema.get("example").is_some() {
+        return Ok(());
+    }
+
+    let matches: bool = match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        _ => true,
+    };
+    if !matches {
+        return Err(format!("{} should be {} but was {}", at, expected, value));
+    }
+
+    if let (Some(fields), Some(properties)) = (value.as_object(), schema["properties"].as_object())
+    {
+        for (name, property) in properties {
+            let required: bool = is_required(schema, name);
+            match fields.get(name) {
+                Some(Value::Null) if !required => {}
+                Some(field) => check_shape(field, property, &format!("{}.{}", at, name))?,
+                None if !required => {}
+                None => return Err(format!("{} is missing field {:?}", at, name)),
+            }
+        }
+    }
+    if let Some(items) = value.as_array() {
+        for (index, item) in items.iter().enumerate() {
+            check_shape(item, &schema["items"], &format!("{}[{}]", at, index))?;
+        }
+    }
+    Ok(())
+}
+
+// Schemas without a "required" list (hand written ones) require every non-nullable property
+fn is_required(schema: &Value, name: &str) -> bool {
+    match schema["required"].as_array() {
+        Some(required) => required.iter().any(|field| field == name),
+        None => schema["properties"][name]["nullable"] != Value::Bool(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn route(method: &str, route: &str, request_body: Value, response: Value) -> RouteObject {
+        RouteObject {
+            route: route.to_string(),
+            is_route_dynamic: route.contains('{'),
+            method: method.to_string(),
+            request_body,
+            response,
+        }
+    }
+
+    #[test]
+    fn tests_endpoint_checks_follow_the_schema() {
+        assert_eq!(sample_path("/task/{id}/tags/{tag}"), "/task/1/tags/1");
+        assert_eq!(sample_path("/task"), "/task");
+
+        let endpoints: Vec<RouteObject> = vec![
+            route("delete", "/task/{id}", json!("None"), json!("None")),
+            route(
+                "get",
+                "/task/{id}",
+                json!("None"),
+                json!({ "id": "number" }),
+            ),
+            route(
+                "post",
+                "/task",
+                json!({ "id": "number", "name": "string", "done": "bool" }),
+                json!("None"),
+            ),
+        ];
+        let methods: Vec<&str> = test_order(&endpoints)
+            .iter()
+            .map(|endpoint| endpoint.method.as_str())
+            .collect();
+        assert_eq!(methods, vec!["post", "get", "delete"]);
+
+        let request_schema: Value = body_schema(&endpoints[2].request_body).unwrap();
+        let request: Value = sample_value(&request_schema);
+        assert_eq!(request, json!({ "id": 1, "name": "sample", "done": true }));
+        assert!(check_shape(&request, &request_schema, "request").is_ok());
+
+        let response_schema: Value =
+            body_schema(&json!([{ "id": "integer", "name": "string" }])).unwrap();
+        assert!(check_shape(
+            &json!([{ "id": 1, "name": "a" }]),
+            &response_schema,
+            "response"
+        )
+        .is_ok());
+        assert_eq!(
+            check_shape(
+                &json!([{ "id": "1", "name": "a" }]),
+                &response_schema,
+                "response"
+            ),
+            Err("response[0].id should be integer but was \"1\"".to_string())
+        );
+        assert_eq!(
+            check_shape(&json!([{ "id": 1 }]), &response_schema, "response"),
+            Err("response[0] is missing field \"name\"".to_string())
+        );
+        assert!(check_shape(
+            &json!("anything"),
+            &body_schema(&json!("not_provided")).unwrap(),
+            "response"
+        )
+        .is_ok());
+
+        let report: EndpointReport = EndpointReport {
+            results: vec![EndpointResult {
+                method: "get".to_string(),
+                route: "/task/{id}".to_string(),
+                url: "http://127.0.0.1:8080/task/1".to_string(),
+                passed: false,
+                status: Some(404),
+                cause: None,
+                detail: "status 404".to_string(),
+            }],
+        };
+        assert_eq!(report.blocking_failures().len(), 1);
+        assert_eq!(report.summary(), "FAIL GET /task/{id}: status 404");
+    }
+
+    #[test]
+    fn tests_null_is_accepted_for_optional_fields() {
+        let schema: Value =
+            body_schema(&json!({ "id": "integer", "note": null, "due": "Option<string>" }))
+                .unwrap();
+        assert!(check_shape(&json!({ "id": 1, "note": null, "due": null }), &schema, "r").is_ok());
+        assert!(check_shape(&json!({ "id": 1 }), &schema, "r").is_ok());
+        assert!(check_shape(&json!({ "id": 1, "due": "today" }), &schema, "r").is_ok());
+        assert_eq!(
+            check_shape(&json!({ "id": 1, "due": 5 }), &schema, "r"),
+            Err("r.due should be string but was 5".to_string())
+        );
+        assert_eq!(
+            check_shape(&json!({ "id": null }), &schema, "r"),
+            Err("r.id should be integer but was null".to_string())
+        );
+    }
+
+    #[test]
+    fn tests_auth_and_external_failures_are_not_blocking() {
+        assert_eq!(
+            failure_cause("/auth/sign-in", 400, "bad credentials"),
+            Some(FailureCause::Auth)
+        );
+        assert_eq!(failure_cause("/task", 401, ""), Some(FailureCause::Auth));
+        assert_eq!(
+            failure_cause(
+                "/weather/{city}",
+                500,
+                "error sending request for url (https://api.weather.com/)"
+            ),
+            Some(FailureCause::ExternalApi)
+        );
+        assert_eq!(failure_cause("/tokens_of_love", 500, ""), None);
+
+        let failure = |route: &str, status: Option<u16>, detail: &str| EndpointResult {
+            method: "get".to_string(),
+            route: route.to_string(),
+            url: format!("http://127.0.0.1:8080{}", route),
+            passed: false,
+            status,
+            cause: None,
+            detail: detail.to_string(),
+        };
+        let mut report: EndpointReport = EndpointReport {
+            results: vec![
+                failure("/quote", None, "request failed: connection closed"),
+                failure("/task", Some(404), "status 404"),
+            ],
+        };
+        report.excuse_network_failures("thread panicked: dns error: failed to lookup address");
+        assert_eq!(report.excused_failures().len(), 1);
+        assert_eq!(report.blocking_failures()[0].route, "/task");
+        assert_eq!(
+            report.summary(),
+            "FAIL (external API, not fatal) GET /quote: request failed: connection closed\n\
+             FAIL GET /task: status 404"
+        );
+    }
+}
@@ -1,22 +1,35 @@
 use crate::ai_functions::frontend::{
-    print_code_bugs_resolution, print_recommended_site_main_colors, print_recommended_site_pages,
+    print_recommended_site_main_colors, print_recommended_site_pages,
     print_recommended_site_pages_with_apis,
 };
-use crate::helpers::command_line::PrintCommand;
-use crate::helpers::utils::{
-    ai_task_request, ai_task_request_decoded, read_frontend_code_contents, save_frontend_code,
-    API_SCHEMA_JSON, WEB_APP_PROJECT_PATH, WEB_SERVER_PROJECT_PATH,
-};
+use crate::apis::llm_provider::LlmProvider;
+use crate::helpers::command_line::{with_stream_echo, PrintCommand};
+use crate::helpers::utils::agent_task_request_decoded;
+use crate::helpers::workspace::Workspace;
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
 use crate::models::agent_basic::basic_traits::BasicTraits;
-use crate::models::agents::agent_frontend_comp::BuildComponent;
-use crate::models::agents::agent_trait::{FactSheet, SpecialFunctions};
+use crate::models::agents::agent_frontend_comp::{BuildComponent, ComponentContext};
+use crate::models::agents::agent_frontend_router::{page_routes, save_controller, save_nav_links};
+use crate::models::agents::agent_trait::{
+    AgentCheckpoint, FactSheet, FactSheetAccess, FactSheetField, RouteObject, SpecialFunctions,
+};
+use crate::models::general::errors::AgentError;
+use crate::models::general::llm::Message;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
-use std::process::{Command, Stdio};
-use strum::IntoEnumIterator;
+use std::env;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::process::Command;
+use tokio::task::JoinSet;
+
+// Components written at the same time unless FRONTEND_CONCURRENCY says otherwise
+const DEFAULT_CONCURRENCY: usize = 4;
+// Rounds of component fixes after the first failed build, as many as the backend developer gets
+const MAX_FIX_ROUNDS: u8 = 2;
+// Installs and builds the web app unless FRONTEND_PACKAGE_MANAGER says otherwise
+const DEFAULT_PACKAGE_MANAGER: &str = "yarn";
 
 // To define what stage the frontend developer is at
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -37,12 +50,6 @@ pub struct APIAssignment {
 // Used for creating a type to be used for decoding shorthand
 type PageRoutes = HashMap<String, Vec<APIAssignment>>;
 
-// Used for decoding page names and suggested content
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PageAPIAssign {
-    pub page: Vec<APIAssignment>,
-}
-
 // Used for decoding page names and suggested content
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SitePages {
@@ -63,12 +70,16 @@ pub struct DesignBuildSheet {
 pub struct AgentFrontendDeveloper {
     pub attributes: BasicAgent,
     pub build_sheet: DesignBuildSheet,
-    pub bug_count: u8,
-    pub operation_focus: BuildComponent,
+    // How many components are generated (and fixed) at once
+    pub concurrency: usize,
+    pub llm: Arc<dyn LlmProvider>,
+    // Run as `<package_manager> install` and `<package_manager> build` in the web app
+    pub package_manager: String,
+    pub workspace: Workspace,
 }
 
 impl AgentFrontendDeveloper {
-    pub fn new() -> Self {
+    pub fn new(llm: Arc<dyn LlmProvider>, workspace: Workspace) -> Self {
         let attributes: BasicAgent = BasicAgent {
             objective: "Develops frontned code for website".to_string(),
             position: "Frontend Developer".to_string(),
@@ -84,12 +95,14 @@ impl AgentFrontendDeveloper {
             build_mode: FrontendBuildMode::Infrastructure,
         };
 
-        return Self {
+        Self {
             attributes,
             build_sheet,
-            bug_count: 0,
-            operation_focus: BuildComponent::Logo,
-        };
+            concurrency: concurrency_from_env(),
+            llm,
+            package_manager: package_manager_from_env(),
+            workspace,
+        }
     }
 
     // Confirms what stage the Frontend Agent is in
@@ -101,43 +114,44 @@ impl AgentFrontendDeveloper {
         }
     }
 
-    async fn get_page_context(&mut self, project_description: &String) {
-        let path: String = format!("{}/src/main.rs", WEB_SERVER_PROJECT_PATH);
-        let backend_code: String =
-            fs::read_to_string(path).expect("Something went wrong reading the file");
-
+    async fn get_page_context(
+        &mut self,
+        project_description: &String,
+        backend_code: &String,
+    ) -> Result<(), AgentError> {
         let msg_context: String = format!(
             "PROJECT_DESCRIPTION: {:?}, CODE_LOGIC: {:?}",
             project_description, backend_code
         );
 
-        let ai_response: Vec<SitePages> = ai_task_request_decoded::<Vec<SitePages>>(
+        let ai_response: Vec<SitePages> = agent_task_request_decoded::<Vec<SitePages>>(
+            self.llm.as_ref(),
+            &mut self.attributes,
             msg_context,
-            &self.attributes.position,
             get_function_string!(print_recommended_site_pages),
             print_recommended_site_pages,
         )
-        .await;
+        .await?;
 
         let pages: Vec<String> = ai_response
             .iter()
-            .filter_map(|item| Some(item.page_name.clone()))
+            .map(|item| item.page_name.clone())
             .collect();
 
         // Assign pages to buildsheet
         self.build_sheet.pages = Some(pages);
         self.build_sheet.pages_descriptions = Some(ai_response);
+        Ok(())
     }
 
     // Assign API Routes to pages
     async fn assign_api_routes(
         &mut self,
         project_description: &String,
+        api_endpoints: &[RouteObject],
         external_api_urls: &Option<Vec<String>>,
-    ) {
-        let path: String = API_SCHEMA_JSON.to_string();
-        let internal_api_endpoints: String =
-            fs::read_to_string(path).expect("Something went wrong reading the file");
+    ) -> Result<(), AgentError> {
+        let internal_api_endpoints: String = serde_json::to_string_pretty(api_endpoints)?;
 
         let external_api_endpoints: String = match external_api_urls {
             Some(endpoints) => format!("{:?}", endpoints),
@@ -157,112 +171,259 @@ impl AgentFrontendDeveloper {
             external_api_endpoints
         );
 
-        let ai_response: PageRoutes = ai_task_request_decoded::<PageRoutes>(
+        let ai_response: PageRoutes = agent_task_request_decoded::<PageRoutes>(
+            self.llm.as_ref(),
+            &mut self.attributes,
             msg_context,
-            &self.attributes.position,
             get_function_string!(print_recommended_site_pages_with_apis),
             print_recommended_site_pages_with_apis,
         )
-        .await;
+        .await?;
 
         self.build_sheet.api_assignments = Some(ai_response);
+        Ok(())
     }
 
-    async fn define_brand_colors(&mut self, project_description: &String) {
+    async fn define_brand_colors(
+        &mut self,
+        project_description: &String,
+    ) -> Result<(), AgentError> {
         let msg_context: String = format!(
             "PROJECT_DESCRIPTION: {}, WEBSITE_CONTENT: {:?}",
             project_description, self.build_sheet.pages_descriptions
         );
 
-        let ai_response: Vec<String> = ai_task_request_decoded::<Vec<String>>(
+        let ai_response: Vec<String> = agent_task_request_decoded::<Vec<String>>(
+            self.llm.as_ref(),
+            &mut self.attributes,
             msg_context,
-            &self.attributes.position,
             get_function_string!(print_recommended_site_main_colors),
             print_recommended_site_main_colors,
         )
-        .await;
+        .await?;
 
         self.build_sheet.brand_colors = Some(ai_response);
+        Ok(())
     }
 
-    async fn run_code_correction(&self, file_path: String, error_code: String) {
-        PrintCommand::UnitTest
-            .print_agent_message(self.attributes.position.as_str(), "Fixing component bugs");
-        let buggy_code: String = read_frontend_code_contents(&file_path);
+    // Creates the components, or fixes them when there are build errors, `concurrency` at a time.
+    // After the first failure no further component is started, the ones already being written are
+    // waited for so their work is kept, and the first error is returned
+    async fn run_components(
+        &mut self,
+        context: &Arc<ComponentContext>,
+        components: Vec<(BuildComponent, Option<String>)>,
+    ) -> Result<(), AgentError> {
+        // Tokens streamed by several calls at once would be interleaved on the terminal
+        let is_echoed: bool = self.concurrency <= 1 || components.len() <= 1;
 
-        let msg_context: String = format!(
-            "ORIGINAL_CODE: {}, ERROR_MESSAGE: {:?}",
-            buggy_code, error_code
-        );
+        let names: Vec<String> = components
+            .iter()
+            .map(|(component, _)| component.name())
+            .collect();
+        let mut memories: Vec<Vec<Message>> = vec![vec![]; components.len()];
+        let mut queue = components.into_iter().enumerate();
+        let mut running: JoinSet<(usize, BasicAgent, Result<(), AgentError>)> = JoinSet::new();
+        let mut failures: Vec<AgentError> = vec![];
+        loop {
+            while failures.is_empty() && running.len() < self.concurrency.max(1) {
+                let (index, (component, build_errors)) = match queue.next() {
+                    Some(next) => next,
+                    None => break,
+                };
+                let context: Arc<ComponentContext> = context.clone();
+                let mut agent: BasicAgent = BasicAgent::new(
+                    self.attributes.objective.clone(),
+                    self.attributes.position.clone(),
+                );
+                running.spawn(with_stream_echo(is_echoed, async move {
+                    let created: Result<(), AgentError> = match build_errors {
+                        Some(build_errors) => {
+                            component
+                                .fix_component(&context, &mut agent, &build_errors)
+                                .await
+                        }
+                        None => component.create_component(&context, &mut agent).await,
+                    };
+                    (index, agent, created)
+                }));
+            }
 
-        let ai_response: String = ai_task_request(
-            msg_context,
-            &self.attributes.position,
-            get_function_string!(print_code_bugs_resolution),
-            print_code_bugs_resolution,
-        )
-        .await;
+            let (index, agent, created) = match running.join_next().await {
+                Some(Ok(finished)) => finished,
+                Some(Err(e)) => {
+                    failures.push(AgentError::from(e));
+                    continue;
+                }
+                None => break,
+            };
+            if let Err(e) = created {
+                PrintCommand::Issue.print_agent_message(
+                    self.attributes.position.as_str(),
+                    format!("{} failed: {}", names[index], e).as_str(),
+                );
+                failures.push(e);
+            }
+            memories[index] = agent.memory;
+        }
+
+        // Kept in component order, however the calls finished
+        for memory in memories {
+            self.attributes.memory.extend(memory);
+        }
+
+        let not_started: Vec<String> = queue.map(|(index, _)| names[index].clone()).collect();
+        if !not_started.is_empty() {
+            PrintCommand::Issue.print_agent_message(
+                self.attributes.position.as_str(),
+                format!("Not started after the failure: {}", not_started.join(", ")).as_str(),
+            );
+        }
+        match failures.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
 
-        save_frontend_code(&file_path, &ai_response);
+    // Workspaces are copied without node_modules, so packages are installed before the first build
+    async fn install_packages(&self) -> Result<(), AgentError> {
+        if self.workspace.web_app_path().join("node_modules").is_dir() {
+            return Ok(());
+        }
+
+        PrintCommand::UnitTest
+            .print_agent_message(self.attributes.position.as_str(), "Installing packages");
+        let install_packages: std::process::Output = Command::new(&self.package_manager)
+            .arg("install")
+            .current_dir(self.workspace.web_app_path())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .await?;
+
+        if !install_packages.status.success() {
+            return Err(AgentError::Build(format!(
+                "{} install failed: {}",
+                self.package_manager,
+                String::from_utf8_lossy(&install_packages.stderr)
+            )));
+        }
+        Ok(())
     }
 
-    async fn perform_component_test(&mut self) -> Result<(), String> {
-        let test_statement: String = format!("Testing Component: {}", self.operation_focus.name());
+    // Ok(None) when the build passes, Ok(Some(output)) with the compiler errors when it fails
+    async fn build_frontend(&self) -> Result<Option<String>, AgentError> {
         PrintCommand::UnitTest
-            .print_agent_message(self.attributes.position.as_str(), test_statement.as_str());
-        let build_frontend_server: std::process::Output = Command::new("yarn")
+            .print_agent_message(self.attributes.position.as_str(), "Testing frontend build");
+        self.install_packages().await?;
+        let build_frontend_server: std::process::Output = Command::new(&self.package_manager)
             .arg("build")
-            .current_dir(WEB_APP_PROJECT_PATH)
+            .current_dir(self.workspace.web_app_path())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .output()
-            .expect("Failed to run component test");
+            .await?;
 
         if build_frontend_server.status.success() {
             PrintCommand::UnitTest.print_agent_message(
                 self.attributes.position.as_str(),
-                "Component build test successful",
+                "Frontend build test successful",
             );
-            self.bug_count = 0;
-            return Ok(());
-        } else {
-            let error_arr: Vec<u8> = build_frontend_server.stderr;
-            let error_str: String = String::from_utf8(error_arr).unwrap();
-
-            self.bug_count += 1;
-            if self.bug_count >= 2 {
-                PrintCommand::Issue.print_agent_message(
-                    self.attributes.position.as_str(),
-                    "Too many code failures",
-                );
-                PrintCommand::Issue.print_agent_message(
-                    self.attributes.position.as_str(),
-                    "Remember: check frontend builds before retrying",
-                );
-                panic!(
-                    "Too many code failed attempts for {}",
-                    self.operation_focus.name()
-                );
-            } else {
-                return Err(error_str);
-            }
+            return Ok(None);
         }
+
+        // tsc reports type errors on stdout
+        Ok(Some(format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&build_frontend_server.stdout),
+            String::from_utf8_lossy(&build_frontend_server.stderr)
+        )))
     }
 }
 
+// FRONTEND_CONCURRENCY=1 writes one component at a time
+fn concurrency_from_env() -> usize {
+    env::var("FRONTEND_CONCURRENCY")
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|concurrency| *concurrency > 0)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+}
+
+// Any package manager that runs package.json scripts as `<manager> build`, i.e. yarn or pnpm
+fn package_manager_from_env() -> String {
+    env::var("FRONTEND_PACKAGE_MANAGER")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|package_manager| !package_manager.is_empty())
+        .unwrap_or(DEFAULT_PACKAGE_MANAGER.to_string())
+}
+
 #[async_trait]
 impl SpecialFunctions for AgentFrontendDeveloper {
     fn get_agent_position(&mut self) -> String {
-        return self.attributes.get_position().clone();
+        self.attributes.get_position().clone()
     }
 
-    async fn execute(
-        &mut self,
-        fact_sheet: &mut FactSheet,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    fn fact_sheet_access(&self) -> FactSheetAccess {
+        FactSheetAccess {
+            reads: vec![
+                FactSheetField::ProjectDescription,
+                FactSheetField::BackendCode,
+                FactSheetField::ApiEndpointSchema,
+            ],
+            optional_reads: vec![FactSheetField::ExternalUrls],
+            writes: vec![],
+        }
+    }
+
+    fn checkpoint(&self) -> AgentCheckpoint {
+        AgentCheckpoint {
+            position: self.attributes.position.clone(),
+            state: self.attributes.state,
+            design_build_sheet: Some(self.build_sheet.clone()),
+        }
+    }
+
+    // A complete build sheet means discovery was paid for, so only the components are redone
+    fn restore(&mut self, checkpoint: &AgentCheckpoint) {
+        if let Some(build_sheet) = &checkpoint.design_build_sheet {
+            self.build_sheet = build_sheet.clone();
+        }
+
+        let is_discovery_done: bool = self.build_sheet.pages.is_some()
+            && self.build_sheet.api_assignments.is_some()
+            && self.build_sheet.brand_colors.is_some();
+
+        self.attributes.state = match checkpoint.state {
+            AgentState::Finished => AgentState::Finished,
+            _ if is_discovery_done => AgentState::Working,
+            _ => AgentState::Discovery,
+        };
+    }
+
+    async fn execute(&mut self, fact_sheet: &mut FactSheet) -> Result<(), AgentError> {
         // Extract required project fact sheet items
         let project_description: &String = &fact_sheet.project_description;
         let external_api_urls: &Option<Vec<String>> = &fact_sheet.external_urls;
+        // Exactly what the backend developer produced, never re-read from disk
+        let backend_code: &String = match &fact_sheet.backend_code {
+            Some(backend_code) => backend_code,
+            None => {
+                return Err(AgentError::MissingInput(
+                    "The fact sheet has no backend code, run the backend developer first"
+                        .to_string(),
+                ))
+            }
+        };
+        let api_endpoints: &Vec<RouteObject> =
+            match &fact_sheet.api_endpoint_schema {
+                Some(api_endpoints) => api_endpoints,
+                None => return Err(AgentError::MissingInput(
+                    "The fact sheet has no API endpoint schema, run the backend developer first"
+                        .to_string(),
+                )),
+            };
 
         // Continue until finished
         // !!! WARNING !!!
@@ -274,12 +435,13 @@ impl SpecialFunctions for AgentFrontendDeveloper {
                     // Confirm Stage
                     self.confirm_stage();
 
-                    self.get_page_context(&project_description).await;
+                    self.get_page_context(project_description, backend_code)
+                        .await?;
 
-                    self.assign_api_routes(&project_description, &external_api_urls)
-                        .await;
+                    self.assign_api_routes(project_description, api_endpoints, external_api_urls)
+                        .await?;
 
-                    self.define_brand_colors(&project_description).await;
+                    self.define_brand_colors(project_description).await?;
 
                     self.attributes.state = AgentState::Working;
                     continue;
@@ -287,38 +449,80 @@ impl SpecialFunctions for AgentFrontendDeveloper {
 
                 // Get pages, api assignments and branding
                 AgentState::Working => {
-                    // Loop through components
-                    for component in BuildComponent::iter() {
-                        // !!!! REMOVE ONLY FOR TESTING !!!
-                        if component != BuildComponent::PageContent1 {
-                            continue;
-                        }
-                        if component == BuildComponent::PageContent2 {
-                            break;
+                    let pages: Vec<String> = self.build_sheet.pages.clone().unwrap_or_default();
+
+                    // The navigation is written against the full link list before any page exists
+                    save_nav_links(&self.workspace, &page_routes(&pages))?;
+
+                    // Write every component, one wave after the other
+                    let context: Arc<ComponentContext> = Arc::new(ComponentContext::new(
+                        self.llm.clone(),
+                        self.workspace.clone(),
+                        self.build_sheet.clone(),
+                        fact_sheet,
+                    ));
+                    let mut components: Vec<BuildComponent> = vec![];
+                    for wave in BuildComponent::build_waves(&pages) {
+                        let tasks: Vec<(BuildComponent, Option<String>)> = wave
+                            .iter()
+                            .map(|component| (component.clone(), None))
+                            .collect();
+                        self.run_components(&context, tasks).await?;
+                        components.extend(wave);
+                    }
+                    save_controller(&self.workspace, &page_routes(&pages))?;
+
+                    // Build, then fix only the components the build complains about, for up to
+                    // MAX_FIX_ROUNDS rounds
+                    let mut fix_rounds: u8 = 0;
+                    while let Some(build_output) = self.build_frontend().await? {
+                        let failing: Vec<(BuildComponent, Option<String>)> = components
+                            .iter()
+                            .filter_map(|component| {
+                                component
+                                    .build_errors(&build_output)
+                                    .map(|errors| (component.clone(), Some(errors)))
+                            })
+                            .collect();
+                        if failing.is_empty() {
+                            return Err(AgentError::Build(format!(
+                                "Frontend build failed outside the generated components: {}",
+                                build_output
+                            )));
                         }
 
-                        // Update current operation focus to component
-                        self.operation_focus = component.clone();
-                        component
-                            .create_component(&self, &project_description)
-                            .await;
-
-                        // Unit test component
-                        let test_res: Result<(), String> = self.perform_component_test().await;
-                        match test_res {
-                            // Continue to next component
-                            Ok(()) => continue,
-
-                            // Fix bugs for current component
-                            Err(err_str) => {
-                                let file_path: String = self.operation_focus.filepath();
-                                self.run_code_correction(file_path, err_str).await;
-
-                                // Perform one more test
-                                let _ = self.perform_component_test().await;
-                                continue;
-                            }
+                        let names: Vec<String> = failing
+                            .iter()
+                            .map(|(component, _)| component.name())
+                            .collect();
+                        if fix_rounds >= MAX_FIX_ROUNDS {
+                            PrintCommand::Issue.print_agent_message(
+                                self.attributes.position.as_str(),
+                                "Too many code failures",
+                            );
+                            PrintCommand::Issue.print_agent_message(
+                                self.attributes.position.as_str(),
+                                "Remember: check frontend builds before retrying",
+                            );
+                            return Err(AgentError::Build(format!(
+                                "Too many code failed attempts for {}: {}",
+                                names.join(", "),
+                                build_output
+                            )));
                         }
+                        fix_rounds += 1;
+
+                        PrintCommand::UnitTest.print_agent_message(
+                            self.attributes.position.as_str(),
+                            format!(
+                                "Fixing component bugs (round {} of {}): {}",
+                                fix_rounds,
+                                MAX_FIX_ROUNDS,
+                                names.join(", ")
+                            )
+                            .as_str(),
+                        );
+                        self.run_components(&context, failing).await?;
                     }
 
                     // Complete
@@ -336,47 +540,191 @@ impl SpecialFunctions for AgentFrontendDeveloper {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::apis::replay_provider::test_provider;
+    use crate::apis::scripted_provider::ScriptedProvider;
+    use crate::helpers::utils::read_api_endpoints;
+    use crate::helpers::workspace::test_workspace;
+    use std::fs;
+    use std::path::Path;
+
+    const COMPONENT: &str = "export default function Component() {\n  return <div />;\n}\n";
+
+    // Answers every call the frontend developer makes, with `true` standing in for yarn so the
+    // build passes without downloading any packages
+    fn scripted_agent() -> (tempfile::TempDir, AgentFrontendDeveloper) {
+        let llm: Arc<dyn LlmProvider> = Arc::new(ScriptedProvider::new(&[
+            (
+                "print_recommended_site_pages",
+                "[{\"page_name\":\"home_page\",\"suggested_content_sections\":{\"banner_section\":\"Today's fitness goals\"}}]",
+            ),
+            (
+                "print_recommended_site_pages_with_apis",
+                "{\"home_page\":[{\"api_route\":\"/progress\",\"method\":\"get\",\"route_type\":\"internal\"}]}",
+            ),
+            (
+                "print_recommended_site_main_colors",
+                "[\"#32a852\",\"#0fa0d1\",\"#d10fcb\"]",
+            ),
+            ("print_svg_logo", "<svg viewBox=\"0 0 10 10\"></svg>"),
+            ("print_completed_logo_with_brand_name_react_component", COMPONENT),
+            ("print_header_navigation_react_component", COMPONENT),
+            ("print_footer_navigation_react_component", COMPONENT),
+            ("print_react_typescript_hook_component", COMPONENT),
+            ("print_html_webpage_content_with_text", "<section>Goals</section>"),
+            ("print_create_react_component_with_api_integration", COMPONENT),
+            ("print_create_full_react_component", COMPONENT),
+            ("print_give_component_fantastic_styling", COMPONENT),
+            ("print_code_bugs_resolution", COMPONENT),
+        ]));
+        let (output_dir, workspace): (tempfile::TempDir, Workspace) = test_workspace();
+        let mut agent: AgentFrontendDeveloper = AgentFrontendDeveloper::new(llm, workspace);
+        agent.package_manager = "true".to_string();
+        (output_dir, agent)
+    }
+
+    fn is_written(agent: &AgentFrontendDeveloper, component: &BuildComponent) -> bool {
+        Path::new(&format!(
+            "{}{}",
+            agent.workspace.web_app_path().display(),
+            component.filepath()
+        ))
+        .is_file()
+    }
+
+    // The hook fails straight away: the fact sheet has no API endpoint schema
+    async fn run_with_failing_hook(
+        concurrency: usize,
+    ) -> (tempfile::TempDir, AgentFrontendDeveloper) {
+        let (output_dir, mut agent): (tempfile::TempDir, AgentFrontendDeveloper) = scripted_agent();
+        agent.concurrency = concurrency;
+        agent.build_sheet.pages = Some(vec!["home_page".to_string()]);
+        let fact_sheet: FactSheet =
+            serde_json::from_str("{\"project_description\":\"Build a todo app\"}").unwrap();
+        let context: Arc<ComponentContext> = Arc::new(ComponentContext::new(
+            agent.llm.clone(),
+            agent.workspace.clone(),
+            agent.build_sheet.clone(),
+            &fact_sheet,
+        ));
+        let components: Vec<(BuildComponent, Option<String>)> = vec![
+            (BuildComponent::ReactHook, None),
+            (BuildComponent::NavHeader, None),
+            (BuildComponent::NavFooter, None),
+        ];
+        let result: Result<(), AgentError> = agent.run_components(&context, components).await;
+        assert!(matches!(result, Err(AgentError::MissingInput(_))));
+        (output_dir, agent)
+    }
+
+    #[tokio::test]
+    async fn components_in_flight_finish_after_a_failure() {
+        // All three start together, the navigation is still written and remembered
+        let (_output_dir, agent) = run_with_failing_hook(4).await;
+        assert_eq!(agent.attributes.memory.len(), 4);
+
+        // One at a time, nothing is started after the hook failed
+        let (_output_dir, agent) = run_with_failing_hook(1).await;
+        assert!(agent.attributes.memory.is_empty());
+    }
 
     #[tokio::test]
     async fn develops_context_and_branding() {
         // Create agent instance and site purpose
-        let mut agent: AgentFrontendDeveloper = AgentFrontendDeveloper::new();
+        let (_output_dir, mut agent): (tempfile::TempDir, AgentFrontendDeveloper) =
+            scripted_agent();
 
         // Initialze Factsheet
-        let mut factsheet: FactSheet = serde_json::from_str("{\"project_description\":\"Build a todo app for a fitness tracking goal\",\"project_scope\":{\"is_crud_required\":true,\"is_user_login_and_logout\":true,\"is_external_urls_required\":true},\"external_urls\":[\"https://api.exchangeratesapi.io/latest\"],\"backend_code\":null,\"frontend_code\":null,\"json_db_schema\":null}").unwrap();
+        let mut factsheet: FactSheet = serde_json::from_str("{\"project_description\":\"Build a todo app for a fitness tracking goal\",\"project_scope\":{\"is_crud_required\":true,\"is_user_login_and_logout\":true,\"is_external_urls_required\":true},\"external_urls\":[\"https://api.exchangeratesapi.io/latest\"],\"backend_code\":\"async fn read_all_progresses() -> impl Responder {}\",\"api_endpoint_schema\":[{\"route\":\"/progress\",\"is_route_dynamic\":false,\"method\":\"get\",\"request_body\":null,\"response\":[{\"id\":\"number\",\"progress_data\":\"string\"}]}]}").unwrap();
 
         // Execute running agent
         agent
             .execute(&mut factsheet)
             .await
             .expect("Unable to execute running agent");
-        dbg!(agent);
+        assert_eq!(agent.attributes.state, AgentState::Finished);
+        assert_eq!(agent.build_sheet.pages, Some(vec!["home_page".to_string()]));
+        assert_eq!(agent.build_sheet.brand_colors.as_ref().unwrap().len(), 3);
+        assert!(is_written(
+            &agent,
+            &BuildComponent::pages(&["home_page".to_string()])[0]
+        ));
+    }
+
+    #[tokio::test]
+    async fn designs_page_context_from_fixtures() {
+        let (_output_dir, workspace): (tempfile::TempDir, Workspace) = test_workspace();
+        let mut agent: AgentFrontendDeveloper =
+            AgentFrontendDeveloper::new(test_provider(), workspace);
+        let project_description: String =
+            "build a website that fetches and displays random images of cats".to_string();
+        let external_urls: Option<Vec<String>> = Some(vec![
+            "https://api.thecatapi.com/v1/images/search".to_string(),
+        ]);
+        // Stand in for the code and schema the backend developer leaves in the fact sheet
+        let backend_code: String = fs::read_to_string(agent.workspace.exec_main_path()).unwrap();
+        let api_endpoints: Vec<RouteObject> = read_api_endpoints(&agent.workspace).unwrap();
+
+        agent
+            .get_page_context(&project_description, &backend_code)
+            .await
+            .expect("Failed to get page context");
+        agent
+            .assign_api_routes(&project_description, &api_endpoints, &external_urls)
+            .await
+            .expect("Failed to assign API routes");
+        agent
+            .define_brand_colors(&project_description)
+            .await
+            .expect("Failed to define brand colors");
+
+        let pages: &Vec<String> = agent.build_sheet.pages.as_ref().unwrap();
+        assert!(!pages.is_empty());
+        assert!(agent
+            .build_sheet
+            .api_assignments
+            .as_ref()
+            .unwrap()
+            .contains_key(&pages[0]));
+        assert!(agent.build_sheet.brand_colors.is_some());
     }
 
     #[tokio::test]
     async fn works_on_shared_components() {
         // Create agent instance and site purpose
-        let mut agent: AgentFrontendDeveloper = AgentFrontendDeveloper::new();
+        let (_output_dir, mut agent): (tempfile::TempDir, AgentFrontendDeveloper) =
+            scripted_agent();
         agent.attributes.state = AgentState::Working;
-        agent.build_sheet.pages = Some(vec!["home_page".to_string(), "about_page".to_string()]);
+        agent.build_sheet = serde_json::from_str("{\"pages\":[\"home_page\",\"about_page\"],\"pages_descriptions\":[{\"page_name\":\"home_page\",\"suggested_content_sections\":{\"banner_section\":\"Today's fitness goals\"}},{\"page_name\":\"about_page\",\"suggested_content_sections\":{\"story_section\":\"Why we track progress\"}}],\"api_assignments\":{\"home_page\":[],\"about_page\":[]},\"brand_colors\":[\"#32a852\"],\"build_mode\":\"PageComponents\"}").unwrap();
 
         // Initialze Factsheet
-        let mut factsheet: FactSheet = serde_json::from_str("{\"project_description\":\"Build a todo app for a fitness tracking goal\",\"project_scope\":{\"is_crud_required\":true,\"is_user_login_and_logout\":true,\"is_external_urls_required\":true},\"external_urls\":[\"https://api.exchangeratesapi.io/latest\"],\"backend_code\":null,\"frontend_code\":null,\"json_db_schema\":null}").unwrap();
+        let mut factsheet: FactSheet = serde_json::from_str("{\"project_description\":\"Build a todo app for a fitness tracking goal\",\"project_scope\":{\"is_crud_required\":true,\"is_user_login_and_logout\":true,\"is_external_urls_required\":true},\"external_urls\":[\"https://api.exchangeratesapi.io/latest\"],\"backend_code\":\"async fn read_all_progresses() -> impl Responder {}\",\"api_endpoint_schema\":[{\"route\":\"/progress\",\"is_route_dynamic\":false,\"method\":\"get\",\"request_body\":null,\"response\":[{\"id\":\"number\",\"progress_data\":\"string\"}]}]}").unwrap();
 
         // Execute running agent
         agent
             .execute(&mut factsheet)
             .await
             .expect("Unable to execute running agent");
-        // dbg!(agent);
+        for component in [
+            BuildComponent::Logo,
+            BuildComponent::NavHeader,
+            BuildComponent::NavFooter,
+            BuildComponent::ReactHook,
+        ] {
+            assert!(
+                is_written(&agent, &component),
+                "{} was not written",
+                component.name()
+            );
+        }
     }
 
     #[tokio::test]
     async fn works_on_final_pages() {
         // Create agent instance and site purpose
-        let mut agent: AgentFrontendDeveloper = AgentFrontendDeveloper::new();
-        let factsheet_str: &str = "{\"project_description\":\"build a website that fetches and tracks fitness progress with timezone information\",\"project_scope\":{\"is_crud_required\":true,\"is_user_login_and_logout\":true,\"is_external_urls_required\":true},\"external_urls\":[\"https://ipapi.co/json\",\"https://wger.de/api/v2/\"],\"backend_code\":\"use actix_cors::Cors;\\nuse actix_web::{http::header, web, App, HttpServer, Responder, HttpResponse};\\nuse serde::{Deserialize, Serialize};\\nuse std::sync::Mutex;\\nuse std::collections::HashMap;\\nuse std::fs;\\nuse std::io::Write;\\nuse reqwest::Client as HttpClient;\\nuse async_trait::async_trait;\\n\\n#[derive(Serialize, Deserialize, Debug, Clone)]\\npub struct FitnessProgress {\\n    pub id: u64,\\n    pub user_id: u64,\\n    pub progress_data: String,\\n    pub timezone: String,\\n}\\n\\n#[derive(Serialize, Deserialize, Debug, Clone)]\\npub struct User {\\n    pub id: u64,\\n    pub username: String,\\n    pub password: String,\\n}\\n\\n#[derive(Serialize, Deserialize, Debug, Clone)]\\nstruct Database {\\n    fitness_progresses: HashMap<u64, FitnessProgress>,\\n    users: HashMap<u64, User>,\\n}\\n\\nimpl Database {\\n    fn new() -> Self {\\n        Self {\\n            fitness_progresses: HashMap::new(),\\n            users: HashMap::new(),\\n        }\\n    }\\n\\n    // FITNESS_PROGRESS CRUD OPERATIONS\\n    fn insert_progress(&mut self, progress: FitnessProgress) {\\n        self.fitness_progresses.insert(progress.id, progress);\\n    }\\n\\n    fn get_progress(&self, id: &u64) -> Option<&FitnessProgress> {\\n        self.fitness_progresses.get(id)\\n    }\\n\\n    fn get_all_progresses(&self) -> Vec<&FitnessProgress> {\\n        self.fitness_progresses.values().collect()\\n    }\\n\\n    fn delete_progress(&mut self, id: &u64) {\\n        self.fitness_progresses.remove(id);\\n    }\\n\\n    fn update_progress(&mut self, progress: FitnessProgress) {\\n        self.fitness_progresses.insert(progress.id, progress);\\n    }\\n\\n    // USER DATA RELATED OPERATIONS\\n    fn insert_user(&mut self, user: User) {\\n        self.users.insert(user.id, user);\\n    }\\n\\n    fn get_user_by_name(&self, username: &str) -> Option<&User> {\\n        self.users.values().find(|u| u.username == username)\\n    }\\n\\n    // DATABASE SAVING\\n    fn save_to_file(&self) -> std::io::Result<()> {\\n        let data = serde_json::to_string(&self)?;\\n        let mut file = fs::File::create(\\\"database.json\\\")?;\\n        file.write_all(data.as_bytes())?;\\n        Ok(())\\n    }\\n\\n    fn load_from_file() -> std::io::Result<Self> {\\n        let file_content = fs::read_to_string(\\\"database.json\\\")?;\\n        let db: Database = serde_json::from_str(&file_content)?;\\n        Ok(db)\\n    }\\n}\\n\\nstruct AppState {\\n    db: Mutex<Database>,\\n    http_client: HttpClient,\\n}\\n\\n#[async_trait]\\ntrait ExternalDataFetcher {\\n    async fn fetch_external_data(&self, url: &str) -> Result<String, reqwest::Error>;\\n}\\n\\n#[async_trait]\\nimpl ExternalDataFetcher for AppState {\\n    async fn fetch_external_data(&self, url: &str) -> Result<String, reqwest::Error> {\\n        let response = self.http_client.get(url).send().await?;\\n        let content = response.text().await?;\\n        Ok(content)\\n    }\\n}\\n\\nasync fn create_progress(\\n    app_state: web::Data<AppState>,\\n    progress: web::Json<FitnessProgress>,\\n) -> impl Responder {\\n    let mut db = app_state.db.lock().unwrap();\\n    db.insert_progress(progress.into_inner());\\n    let _ = db.save_to_file();\\n    HttpResponse::Ok().finish()\\n}\\n\\nasync fn read_progress(app_state: web::Data<AppState>, id: web::Path<u64>) -> impl Responder {\\n    let db = app_state.db.lock().unwrap();\\n    match db.get_progress(&id.into_inner()) {\\n        Some(progress) => HttpResponse::Ok().json(progress),\\n        None => HttpResponse::NotFound().finish(),\\n    }\\n}\\n\\nasync fn read_all_progresses(app_state: web::Data<AppState>) -> impl Responder {\\n    let db = app_state.db.lock().unwrap();\\n    let progresses = db.get_all_progresses();\\n    HttpResponse::Ok().json(progresses)\\n}\\n\\nasync fn update_progress(\\n    app_state: web::Data<AppState>,\\n    progress: web::Json<FitnessProgress>,\\n) -> impl Responder {\\n    let mut db = app_state.db.lock().unwrap();\\n    db.update_progress(progress.into_inner());\\n    let _ = db.save_to_file();\\n    HttpResponse::Ok().finish()\\n}\\n\\nasync fn delete_progress(app_state: web::Data<AppState>, id: web::Path<u64>) -> impl Responder {\\n    let mut db = app_state.db.lock().unwrap();\\n    db.delete_progress(&id.into_inner());\\n    let _ = db.save_to_file();\\n    HttpResponse::Ok().finish()\\n}\\n\\nasync fn register(app_state: web::Data<AppState>, user: web::Json<User>) -> impl Responder {\\n    let mut db = app_state.db.lock().unwrap();\\n    db.insert_user(user.into_inner());\\n    let _ = db.save_to_file();\\n    HttpResponse::Ok().finish()\\n}\\n\\nasync fn login(app_state: web::Data<AppState>, user: web::Json<User>) -> impl Responder {\\n    let db = app_state.db.lock().unwrap();\\n\\n    match db.get_user_by_name(&user.username) {\\n        Some(stored_user) if stored_user.password == user.password => {\\n            HttpResponse::Ok().body(\\\"Logged in!\\\")\\n        }\\n        _ => HttpResponse::BadRequest().body(\\\"Invalid username or password\\\"),\\n    }\\n}\\n\\n#[actix_web::main]\\nasync fn main() -> std::io::Result<()> {\\n    let db = match Database::load_from_file() {\\n        Ok(db) => db,\\n        Err(_) => Database::new(),\\n    };\\n\\n    let data = web::Data::new(AppState {\\n        db: Mutex::new(db),\\n        http_client: HttpClient::new(),\\n    });\\n\\n    HttpServer::new(move || {\\n        App::new()\\n            .wrap(\\n                Cors::permissive()\\n                    .allowed_origin_fn(|origin, _req_head| {\\n                        origin.as_bytes().starts_with(b\\\"http://localhost:\\\") || origin == \\\"null\\\"\\n                    })\\n                    .allowed_methods(vec![\\\"GET\\\", \\\"POST\\\", \\\"PUT\\\", \\\"DELETE\\\"])\\n                    .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT])\\n                    .allowed_header(header::CONTENT_TYPE)\\n                    .supports_credentials()\\n                    .max_age(3600),\\n            )\\n            .app_data(data.clone())\\n            .route(\\\"/progress\\\", web::post().to(create_progress))\\n            .route(\\\"/progress\\\", web::get().to(read_all_progresses))\\n            .route(\\\"/progress/{id}\\\", web::get().to(read_progress))\\n            .route(\\\"/progress/{id}\\\", web::put().to(update_progress))\\n            .route(\\\"/progress/{id}\\\", web::delete().to(delete_progress))\\n            .route(\\\"/register\\\", web::post().to(register))\\n            .route(\\\"/login\\\", web::post().to(login))\\n    })\\n    .bind(\\\"127.0.0.1:8080\\\")?\\n    .run()\\n    .await\\n}\",\"api_endpoint_schema\":[{\"is_route_dynamic\":\"false\",\"method\":\"get\",\"request_body\":\"None\",\"response\":\"Array\",\"route\":\"/progress\"}]}";
-        let buildsheet_str: &str = "{\"pages\":[\"home_page\",\"progress_dashboard\"],\"pages_descriptons\":[{\"page_name\":\"home_page\",\"suggested_content_sections\":{\"banner_section\":\"Catchy title and subtitle showcasing the fitness progress tracking features\",\"call_to_action_section\":\"Encourage users to sign up and start tracking their fitness progress\",\"features_section\":\"Display key features of the website with icons and short descriptions\"}},{\"page_name\":\"progress_dashboard\",\"suggested_content_sections\":{\"add_progress_section\":\"Provide a form for the user to input new fitness progress data\",\"fitness_progress_section\":\"Display a visual representation of the user's fitness progress over time\",\"user_info_section\":\"Display user's name, timezone info and greetings based on the time of the day\"}}],\"api_assignments\":{\"home_page\":[{\"api_route\":\"/register\",\"method\":\"post\",\"route_type\":\"internal\"},{\"api_route\":\"/login\",\"method\":\"post\",\"route_type\":\"internal\"},{\"api_route\":\"https://ipapi.co/json\",\"method\":\"get\",\"route_type\":\"external\"}],\"progress_dashboard\":[{\"api_route\":\"/progress\",\"method\":\"post\",\"route_type\":\"internal\"},{\"api_route\":\"/progress\",\"method\":\"get\",\"route_type\":\"internal\"},{\"api_route\":\"/progress/{id}\",\"method\":\"get\",\"route_type\":\"internal\"},{\"api_route\":\"/progress/{id}\",\"method\":\"put\",\"route_type\":\"internal\"},{\"api_route\":\"/progress/{id}\",\"method\":\"delete\",\"route_type\":\"internal\"},{\"api_route\":\"https://wger.de/api/v2/\",\"method\":\"get\",\"route_type\":\"external\"}]},\"brand_colours\":[\"#32a852\",\"#0fa0d1\",\"#d10fcb\"],\"build_mode\":\"Infrastructure\"}";
+        let (_output_dir, mut agent): (tempfile::TempDir, AgentFrontendDeveloper) =
+            scripted_agent();
+        let factsheet_str: &str = "{\"project_description\":\"build a website that fetches and tracks fitness progress with timezone information\",\"project_scope\":{\"is_crud_required\":true,\"is_user_login_and_logout\":true,\"is_external_urls_required\":true},\"external_urls\":[\"https://ipapi.co/json\",\"https://wger.de/api/v2/\"],\"backend_code\":\"use actix_cors::Cors;\\nuse actix_web::{http::header, web, App, HttpServer, Responder, HttpResponse};\\nuse serde::{Deserialize, Serialize};\\nuse std::sync::Mutex;\\nuse std::collections::HashMap;\\nuse std::fs;\\nuse std::io::Write;\\nuse reqwest::Client as HttpClient;\\nuse async_trait::async_trait;\\n\\n#[derive(Serialize, Deserialize, Debug, Clone)]\\npub struct FitnessProgress {\\n    pub id: u64,\\n    pub user_id: u64,\\n    pub progress_data: String,\\n    pub timezone: String,\\n}\\n\\n#[derive(Serialize, Deserialize, Debug, Clone)]\\npub struct User {\\n    pub id: u64,\\n    pub username: String,\\n    pub password: String,\\n}\\n\\n#[derive(Serialize, Deserialize, Debug, Clone)]\\nstruct Database {\\n    fitness_progresses: HashMap<u64, FitnessProgress>,\\n    users: HashMap<u64, User>,\\n}\\n\\nimpl Database {\\n    fn new() -> Self {\\n        Self {\\n            fitness_progresses: HashMap::new(),\\n            users: HashMap::new(),\\n        }\\n    }\\n\\n    // FITNESS_PROGRESS CRUD OPERATIONS\\n    fn insert_progress(&mut self, progress: FitnessProgress) {\\n        self.fitness_progresses.insert(progress.id, progress);\\n    }\\n\\n    fn get_progress(&self, id: &u64) -> Option<&FitnessProgress> {\\n        self.fitness_progresses.get(id)\\n    }\\n\\n    fn get_all_progresses(&self) -> Vec<&FitnessProgress> {\\n        self.fitness_progresses.values().collect()\\n    }\\n\\n    fn delete_progress(&mut self, id: &u64) {\\n        self.fitness_progresses.remove(id);\\n    }\\n\\n    fn update_progress(&mut self, progress: FitnessProgress) {\\n        self.fitness_progresses.insert(progress.id, progress);\\n    }\\n\\n    // USER DATA RELATED OPERATIONS\\n    fn insert_user(&mut self, user: User) {\\n        self.users.insert(user.id, user);\\n    }\\n\\n    fn get_user_by_name(&self, username: &str) -> Option<&User> {\\n        self.users.values().find(|u| u.username == username)\\n    }\\n\\n    // DATABASE SAVING\\n    fn save_to_file(&self) -> std::io::Result<()> {\\n        let data = serde_json::to_string(&self)?;\\n        let mut file = fs::File::create(\\\"database.json\\\")?;\\n        file.write_all(data.as_bytes())?;\\n        Ok(())\\n    }\\n\\n    fn load_from_file() -> std::io::Result<Self> {\\n        let file_content = fs::read_to_string(\\\"database.json\\\")?;\\n        let db: Database = serde_json::from_str(&file_content)?;\\n        Ok(db)\\n    }\\n}\\n\\nstruct AppState {\\n    db: Mutex<Database>,\\n    http_client: HttpClient,\\n}\\n\\n#[async_trait]\\ntrait ExternalDataFetcher {\\n    async fn fetch_external_data(&self, url: &str) -> Result<String, reqwest::Error>;\\n}\\n\\n#[async_trait]\\nimpl ExternalDataFetcher for AppState {\\n    async fn fetch_external_data(&self, url: &str) -> Result<String, reqwest::Error> {\\n        let response = self.http_client.get(url).send().await?;\\n        let content = response.text().await?;\\n        Ok(content)\\n    }\\n}\\n\\nasync fn create_progress(\\n    app_state: web::Data<AppState>,\\n    progress: web::Json<FitnessProgress>,\\n) -> impl Responder {\\n    let mut db = app_state.db.lock().unwrap();\\n    db.insert_progress(progress.into_inner());\\n    let _ = db.save_to_file();\\n    HttpResponse::Ok().finish()\\n}\\n\\nasync fn read_progress(app_state: web::Data<AppState>, id: web::Path<u64>) -> impl Responder {\\n    let db = app_state.db.lock().unwrap();\\n    match db.get_progress(&id.into_inner()) {\\n        Some(progress) => HttpResponse::Ok().json(progress),\\n        None => HttpResponse::NotFound().finish(),\\n    }\\n}\\n\\nasync fn read_all_progresses(app_state: web::Data<AppState>) -> impl Responder {\\n    let db = app_state.db.lock().unwrap();\\n    let progresses = db.get_all_progresses();\\n    HttpResponse::Ok().json(progresses)\\n}\\n\\nasync fn update_progress(\\n    app_state: web::Data<AppState>,\\n    progress: web::Json<FitnessProgress>,\\n) -> impl Responder {\\n    let mut db = app_state.db.lock().unwrap();\\n    db.update_progress(progress.into_inner());\\n    let _ = db.save_to_file();\\n    HttpResponse::Ok().finish()\\n}\\n\\nasync fn delete_progress(app_state: web::Data<AppState>, id: web::Path<u64>) -> impl Responder {\\n    let mut db = app_state.db.lock().unwrap();\\n    db.delete_progress(&id.into_inner());\\n    let _ = db.save_to_file();\\n    HttpResponse::Ok().finish()\\n}\\n\\nasync fn register(app_state: web::Data<AppState>, user: web::Json<User>) -> impl Responder {\\n    let mut db = app_state.db.lock().unwrap();\\n    db.insert_user(user.into_inner());\\n    let _ = db.save_to_file();\\n    HttpResponse::Ok().finish()\\n}\\n\\nasync fn login(app_state: web::Data<AppState>, user: web::Json<User>) -> impl Responder {\\n    let db = app_state.db.lock().unwrap();\\n\\n    match db.get_user_by_name(&user.username) {\\n        Some(stored_user) if stored_user.password == user.password => {\\n            HttpResponse::Ok().body(\\\"Logged in!\\\")\\n        }\\n        _ => HttpResponse::BadRequest().body(\\\"Invalid username or password\\\"),\\n    }\\n}\\n\\n#[actix_web::main]\\nasync fn main() -> std::io::Result<()> {\\n    let db = match Database::load_from_file() {\\n        Ok(db) => db,\\n        Err(_) => Database::new(),\\n    };\\n\\n    let data = web::Data::new(AppState {\\n        db: Mutex::new(db),\\n        http_client: HttpClient::new(),\\n    });\\n\\n    HttpServer::new(move || {\\n        App::new()\\n            .wrap(\\n                Cors::permissive()\\n                    .allowed_origin_fn(|origin, _req_head| {\\n                        origin.as_bytes().starts_with(b\\\"http://localhost:\\\") || origin == \\\"null\\\"\\n                    })\\n                    .allowed_methods(vec![\\\"GET\\\", \\\"POST\\\", \\\"PUT\\\", \\\"DELETE\\\"])\\n                    .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT])\\n                    .allowed_header(header::CONTENT_TYPE)\\n                    .supports_credentials()\\n                    .max_age(3600),\\n            )\\n            .app_data(data.clone())\\n            .route(\\\"/progress\\\", web::post().to(create_progress))\\n            .route(\\\"/progress\\\", web::get().to(read_all_progresses))\\n            .route(\\\"/progress/{id}\\\", web::get().to(read_progress))\\n            .route(\\\"/progress/{id}\\\", web::put().to(update_progress))\\n            .route(\\\"/progress/{id}\\\", web::delete().to(delete_progress))\\n            .route(\\\"/register\\\", web::post().to(register))\\n            .route(\\\"/login\\\", web::post().to(login))\\n    })\\n    .bind(\\\"127.0.0.1:8080\\\")?\\n    .run()\\n    .await\\n}\",\"api_endpoint_schema\":[{\"is_route_dynamic\":false,\"method\":\"get\",\"request_body\":\"None\",\"response\":\"Array\",\"route\":\"/progress\"}]}";
+        let buildsheet_str: &str = "{\"pages\":[\"home_page\",\"progress_dashboard\"],\"pages_descriptions\":[{\"page_name\":\"home_page\",\"suggested_content_sections\":{\"banner_section\":\"Catchy title and subtitle showcasing the fitness progress tracking features\",\"call_to_action_section\":\"Encourage users to sign up and start tracking their fitness progress\",\"features_section\":\"Display key features of the website with icons and short descriptions\"}},{\"page_name\":\"progress_dashboard\",\"suggested_content_sections\":{\"add_progress_section\":\"Provide a form for the user to input new fitness progress data\",\"fitness_progress_section\":\"Display a visual representation of the user's fitness progress over time\",\"user_info_section\":\"Display user's name, timezone info and greetings based on the time of the day\"}}],\"api_assignments\":{\"home_page\":[{\"api_route\":\"/register\",\"method\":\"post\",\"route_type\":\"internal\"},{\"api_route\":\"/login\",\"method\":\"post\",\"route_type\":\"internal\"},{\"api_route\":\"https://ipapi.co/json\",\"method\":\"get\",\"route_type\":\"external\"}],\"progress_dashboard\":[{\"api_route\":\"/progress\",\"method\":\"post\",\"route_type\":\"internal\"},{\"api_route\":\"/progress\",\"method\":\"get\",\"route_type\":\"internal\"},{\"api_route\":\"/progress/{id}\",\"method\":\"get\",\"route_type\":\"internal\"},{\"api_route\":\"/progress/{id}\",\"method\":\"put\",\"route_type\":\"internal\"},{\"api_route\":\"/progress/{id}\",\"method\":\"delete\",\"route_type\":\"internal\"},{\"api_route\":\"https://wger.de/api/v2/\",\"method\":\"get\",\"route_type\":\"external\"}]},\"brand_colors\":[\"#32a852\",\"#0fa0d1\",\"#d10fcb\"],\"build_mode\":\"Infrastructure\"}";
         let mut factsheet: FactSheet = serde_json::from_str(factsheet_str).unwrap();
         let buildsheet: DesignBuildSheet = serde_json::from_str(buildsheet_str).unwrap();
         agent.attributes.state = AgentState::Working;
@@ -386,6 +734,12 @@ pub mod tests {
             .execute(&mut factsheet)
             .await
             .expect("Unable to execute running agent");
-        agent.attributes.state = AgentState::Working;
+        for component in BuildComponent::pages(agent.build_sheet.pages.as_ref().unwrap()) {
+            assert!(
+                is_written(&agent, &component),
+                "{} was not written",
+                component.name()
+            );
+        }
     }
 }
@@ -1,86 +1,217 @@
 use crate::ai_functions::frontend::{
-    print_completed_logo_with_brand_name_react_component, print_create_full_react_component,
-    print_create_react_component_with_API_integration, print_footer_navigation_react_component,
-    print_give_component_fantastic_styling, print_header_navigation_react_component,
-    print_html_webpage_content_with_text, print_react_typescript_hook_component, print_svg_logo,
+    print_code_bugs_resolution, print_completed_logo_with_brand_name_react_component,
+    print_create_full_react_component, print_create_react_component_with_api_integration,
+    print_footer_navigation_react_component, print_give_component_fantastic_styling,
+    print_header_navigation_react_component, print_html_webpage_content_with_text,
+    print_react_typescript_hook_component, print_svg_logo,
 };
-use crate::helpers::utils::{
-    ai_task_request, read_frontend_code_contents, save_frontend_code, WEB_SERVER_PROJECT_PATH,
-};
-use crate::models::agents::agent_frontend::AgentFrontendDeveloper;
+use crate::apis::llm_provider::LlmProvider;
+use crate::helpers::utils::{agent_task_request, read_frontend_code_contents, save_frontend_code};
+use crate::helpers::workspace::Workspace;
+use crate::models::agent_basic::basic_agent::BasicAgent;
+use crate::models::agents::agent_frontend::DesignBuildSheet;
+use crate::models::agents::agent_frontend_router::{nav_links_code, page_routes};
+use crate::models::agents::agent_trait::{FactSheet, RouteObject};
+use crate::models::general::errors::AgentError;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use strum_macros::EnumIter;
+use std::sync::Arc;
+
+// Written before the pages, so every page can use the logo, navigation and API hook
+const SHARED_COMPONENTS: [BuildComponent; 4] = [
+    BuildComponent::Logo,
+    BuildComponent::NavHeader,
+    BuildComponent::NavFooter,
+    BuildComponent::ReactHook,
+];
+
+// Everything a component needs once discovery is done, shared by components generated concurrently
+#[derive(Debug, Clone)]
+pub struct ComponentContext {
+    pub llm: Arc<dyn LlmProvider>,
+    pub workspace: Workspace,
+    pub project_description: String,
+    pub build_sheet: DesignBuildSheet,
+    pub api_endpoint_schema: Option<Vec<RouteObject>>,
+}
+
+impl ComponentContext {
+    pub fn new(
+        llm: Arc<dyn LlmProvider>,
+        workspace: Workspace,
+        build_sheet: DesignBuildSheet,
+        fact_sheet: &FactSheet,
+    ) -> Self {
+        Self {
+            llm,
+            workspace,
+            project_description: fact_sheet.project_description.clone(),
+            build_sheet,
+            api_endpoint_schema: fact_sheet.api_endpoint_schema.clone(),
+        }
+    }
+}
 
 // Used for decoding page names and suggested content
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, EnumIter)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum BuildComponent {
     Logo,
     NavHeader,
     NavFooter,
     ReactHook,
-    PageContent1,
-    PageContent2,
+    // One per page in the design build sheet, `component` is the React component (and file) name
+    Page { index: usize, component: String },
 }
 
 impl BuildComponent {
-    pub fn name(&self) -> &'static str {
-        match *self {
-            BuildComponent::Logo => "Logo",
-            BuildComponent::NavHeader => "NavHeader",
-            BuildComponent::NavFooter => "NavFooter",
-            BuildComponent::ReactHook => "ReactHook",
-            BuildComponent::PageContent1 => "PageContent1",
-            BuildComponent::PageContent2 => "PageContent2",
+    // Every component the frontend developer writes, in waves whose components can be written
+    // concurrently. Pages call the API through the React hook, so it is written first
+    pub fn build_waves(pages: &[String]) -> Vec<Vec<BuildComponent>> {
+        let mut components: Vec<BuildComponent> = SHARED_COMPONENTS
+            .iter()
+            .filter(|component| **component != BuildComponent::ReactHook)
+            .cloned()
+            .collect();
+        components.extend(Self::pages(pages));
+        vec![vec![BuildComponent::ReactHook], components]
+    }
+
+    // Page components named after their page (i.e. "progress_dashboard" => ProgressDashboard)
+    pub fn pages(pages: &[String]) -> Vec<BuildComponent> {
+        let mut components: Vec<BuildComponent> = vec![];
+        for (index, page) in pages.iter().enumerate() {
+            let mut component: String = page_component_name(page);
+            let is_taken = |name: &String| -> bool {
+                components.iter().any(|existing| &existing.name() == name)
+                    || SHARED_COMPONENTS
+                        .iter()
+                        .any(|shared| &shared.name() == name)
+            };
+            if component.is_empty() || is_taken(&component) {
+                component = format!("{}Page{}", component, index + 1);
+            }
+            components.push(BuildComponent::Page { index, component });
+        }
+        components
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            BuildComponent::Logo => "Logo".to_string(),
+            BuildComponent::NavHeader => "NavHeader".to_string(),
+            BuildComponent::NavFooter => "NavFooter".to_string(),
+            BuildComponent::ReactHook => "ReactHook".to_string(),
+            BuildComponent::Page { component, .. } => component.clone(),
         }
     }
 
     pub fn filepath(&self) -> String {
-        match *self {
+        match self {
             BuildComponent::Logo => "/src/components/shared/Logo.tsx".to_string(),
             BuildComponent::NavHeader => "/src/components/shared/Navigation.tsx".to_string(),
             BuildComponent::NavFooter => "/src/components/shared/Footer.tsx".to_string(),
             BuildComponent::ReactHook => "/src/hooks/useCall.tsx".to_string(),
-            BuildComponent::PageContent1 => "/src/components/pages/PageOne.tsx".to_string(),
-            BuildComponent::PageContent2 => "/src/components/pages/PageTwo.tsx".to_string(),
+            BuildComponent::Page { component, .. } => {
+                format!("/src/components/pages/{}.tsx", component)
+            }
         }
     }
 
+    // The part of a failed build's output about this component, None when it is not mentioned
+    // i.e. "src/components/pages/Home.tsx(12,5): error TS2304: Cannot find name 'x'."
+    pub fn build_errors(&self, build_output: &str) -> Option<String> {
+        let file_path: String = self.filepath().trim_start_matches('/').to_string();
+        let mut errors: Vec<&str> = vec![];
+        let mut in_error: bool = false;
+        for line in build_output.lines() {
+            if line.contains(&file_path) {
+                in_error = true;
+            } else if !line.starts_with(char::is_whitespace) || line.trim().is_empty() {
+                in_error = false;
+            }
+            if in_error {
+                errors.push(line);
+            }
+        }
+        match errors.is_empty() {
+            true => None,
+            false => Some(errors.join("\n")),
+        }
+    }
+
+    // Rewrites the component so it no longer causes the build errors
+    pub async fn fix_component(
+        &self,
+        context: &ComponentContext,
+        agent: &mut BasicAgent,
+        build_errors: &str,
+    ) -> Result<(), AgentError> {
+        let buggy_code: String = read_frontend_code_contents(&context.workspace, &self.filepath())?;
+
+        let msg_context: String = format!(
+            "ORIGINAL_CODE: {}, ERROR_MESSAGE: {:?}",
+            buggy_code, build_errors
+        );
+
+        return self
+            .create_and_save(
+                context.llm.as_ref(),
+                agent,
+                &context.workspace,
+                msg_context,
+                print_code_bugs_resolution,
+            )
+            .await;
+    }
+
     // Create component
-    async fn create_and_save(&self, msg_context: String, ai_function: fn(&str) -> &'static str) {
+    async fn create_and_save(
+        &self,
+        llm: &dyn LlmProvider,
+        agent: &mut BasicAgent,
+        workspace: &Workspace,
+        msg_context: String,
+        ai_function: fn(&str) -> &'static str,
+    ) -> Result<(), AgentError> {
         // Retrieve AI Reponse
         let ai_response: String =
-            ai_task_request(msg_context, "Component Writer", &self.name(), ai_function).await;
+            agent_task_request(llm, agent, &[], msg_context, &self.name(), ai_function).await?;
 
         // Save Component
-        save_frontend_code(&self.filepath(), &ai_response);
+        save_frontend_code(workspace, &self.filepath(), &ai_response)
     }
 
     // Prepare and create component
     pub async fn create_component(
         &self,
-        agent: &AgentFrontendDeveloper,
-        project_description: &String,
-    ) {
+        context: &ComponentContext,
+        agent: &mut BasicAgent,
+    ) -> Result<(), AgentError> {
+        let project_description: &String = &context.project_description;
+
         // Extract pages
-        let pages: &Vec<String> = agent.build_sheet.pages.as_ref().expect("Missing pages");
+        let pages: &Vec<String> = match context.build_sheet.pages.as_ref() {
+            Some(pages) => pages,
+            None => return Err(AgentError::MissingInput("Missing pages".to_string())),
+        };
 
         match self {
             Self::Logo => {
                 // Create SVG: Structure message
                 let msg_context: String = format!(
                     "PROJECT_DESCRIPTION: {}, BRAND_COLOURS: {:?}",
-                    project_description, agent.build_sheet.brand_colors
+                    project_description, context.build_sheet.brand_colors
                 );
 
                 // Create SVG: Retrieve AI Reponse
-                let ai_response_svg_logo: String = ai_task_request(
+                let ai_response_svg_logo: String = agent_task_request(
+                    context.llm.as_ref(),
+                    agent,
+                    &[],
                     msg_context,
-                    "Component Writer",
                     get_function_string!(print_svg_logo),
                     print_svg_logo,
                 )
-                .await;
+                .await?;
 
                 // Create SVG: Structure message for logo creation
                 let msg_context: String = format!(
@@ -93,10 +224,13 @@ impl BuildComponent {
 
                 // Create Component
                 self.create_and_save(
+                    context.llm.as_ref(),
+                    agent,
+                    &context.workspace,
                     msg_context,
                     print_completed_logo_with_brand_name_react_component,
                 )
-                .await;
+                .await?;
             }
 
             Self::NavHeader | Self::NavFooter => {
@@ -104,60 +238,99 @@ impl BuildComponent {
                 let msg_context: String = format!(
                     "WEBSITE_SPECIFICATION: {{
             PROJECT_DESCRIPTION: {},
-            PAGES_WHICH_NEED_LINKS: {:?},
+            NAV_LINKS_MODULE: {:?},
             COLOUR_SCHEME: {:?}
           }}",
-                    project_description, pages, agent.build_sheet.brand_colors
+                    project_description,
+                    nav_links_code(&page_routes(pages)),
+                    context.build_sheet.brand_colors
                 );
 
                 // Create and Save
                 if self.name() == "NavHeader" {
-                    self.create_and_save(msg_context, print_header_navigation_react_component)
-                        .await;
+                    self.create_and_save(
+                        context.llm.as_ref(),
+                        agent,
+                        &context.workspace,
+                        msg_context,
+                        print_header_navigation_react_component,
+                    )
+                    .await?;
                 } else {
-                    self.create_and_save(msg_context, print_footer_navigation_react_component)
-                        .await;
+                    self.create_and_save(
+                        context.llm.as_ref(),
+                        agent,
+                        &context.workspace,
+                        msg_context,
+                        print_footer_navigation_react_component,
+                    )
+                    .await?;
                 }
             }
 
             Self::ReactHook => {
-                // Initialize
-                let path: String = format!("{}/api_endpoints.json", WEB_SERVER_PROJECT_PATH);
-                let api_endpoints: String =
-                    fs::read_to_string(path).expect("Something went wrong reading the file");
+                // Initialize from the schema the backend developer put in the fact sheet
+                let api_endpoints: String = match &context.api_endpoint_schema {
+                    Some(api_endpoints) => serde_json::to_string_pretty(api_endpoints)?,
+                    None => {
+                        return Err(AgentError::MissingInput(
+                            "Missing API endpoint schema".to_string(),
+                        ))
+                    }
+                };
 
                 // Create and Save
                 self.create_and_save(
+                    context.llm.as_ref(),
+                    agent,
+                    &context.workspace,
                     format!("API_ENDPOINTS_JSON_SCHEMA: {}", api_endpoints),
                     print_react_typescript_hook_component,
                 )
-                .await;
+                .await?;
             }
 
-            Self::PageContent1 | Self::PageContent2 => {
+            Self::Page { index, .. } => {
                 // Extract page name
-                let (page_name, page_index) = match self.name() {
-                    "PageContent1" => (&pages[0], 0),
-                    "PageContent2" => (&pages[1], 1),
-                    _ => panic!("Page not recognised"),
+                let page_index: usize = *index;
+                let page_name: &String = match pages.get(page_index) {
+                    Some(page_name) => page_name,
+                    None => {
+                        return Err(AgentError::MissingInput(format!(
+                            "No page recommended for {}",
+                            self.name()
+                        )))
+                    }
                 };
 
                 // Extract page input information
-                let file_path: String = self.filepath();
-                dbg!(&file_path);
-                let react_hook_contents: String = read_frontend_code_contents(&file_path);
+                let file_path: String = BuildComponent::ReactHook.filepath();
+                let react_hook_contents: String =
+                    read_frontend_code_contents(&context.workspace, &file_path)?;
 
-                let page_api_endpoints = agent
+                let page_api_endpoints = match context.build_sheet.api_assignments.as_ref() {
+                    Some(api_assignments) => api_assignments.get(page_name),
+                    None => {
+                        return Err(AgentError::MissingInput(
+                            "Missing page API assignments".to_string(),
+                        ))
+                    }
+                };
+
+                let page_description: String = match context
                     .build_sheet
-                    .api_assignments
+                    .pages_descriptions
                     .as_ref()
-                    .unwrap()
-                    .get(page_name);
-
-                let page_description: String = agent.build_sheet.pages_descriptions.as_ref().unwrap()
-                    [page_index]
-                    .suggested_content_sections
-                    .to_string();
+                    .and_then(|descriptions| descriptions.get(page_index))
+                {
+                    Some(description) => description.suggested_content_sections.to_string(),
+                    None => {
+                        return Err(AgentError::MissingInput(format!(
+                            "Missing page description for {}",
+                            page_name
+                        )))
+                    }
+                };
 
                 // Initialize Page HTML Content and Wireframe
                 let msg_context: String = format!(
@@ -169,13 +342,15 @@ impl BuildComponent {
                 );
 
                 // Create Wireframe and Content
-                let wireframe_content: String = ai_task_request(
+                let wireframe_content: String = agent_task_request(
+                    context.llm.as_ref(),
+                    agent,
+                    &[],
                     msg_context,
-                    "Component Page Writer",
                     get_function_string!(print_html_webpage_content_with_text),
                     print_html_webpage_content_with_text,
                 )
-                .await;
+                .await?;
 
                 // Initialize Page API Hook Integration
                 let msg_context: String = format!(
@@ -187,13 +362,15 @@ impl BuildComponent {
                 );
 
                 // React API Display Content
-                let react_api_component_content: String = ai_task_request(
+                let react_api_component_content: String = agent_task_request(
+                    context.llm.as_ref(),
+                    agent,
+                    &[],
                     msg_context,
-                    "Component Page Writer",
-                    get_function_string!(print_create_react_component_with_API_integration),
-                    print_create_react_component_with_API_integration,
+                    get_function_string!(print_create_react_component_with_api_integration),
+                    print_create_react_component_with_api_integration,
                 )
-                .await;
+                .await?;
 
                 // Initialize create full react component
                 let msg_context: String = format!(
@@ -203,21 +380,111 @@ impl BuildComponent {
                 );
 
                 // Create Full React Component
-                let combined_react_component: String = ai_task_request(
+                let combined_react_component: String = agent_task_request(
+                    context.llm.as_ref(),
+                    agent,
+                    &[],
                     msg_context,
-                    "Component Page Writer",
                     get_function_string!(print_create_full_react_component),
                     print_create_full_react_component,
                 )
-                .await;
+                .await?;
 
                 // Initialize create full react component
                 let msg_context: String = format!("REACT_COMPONENT: {}", combined_react_component);
 
                 // Create Component
-                self.create_and_save(msg_context, print_give_component_fantastic_styling)
-                    .await;
+                self.create_and_save(
+                    context.llm.as_ref(),
+                    agent,
+                    &context.workspace,
+                    msg_context,
+                    print_give_component_fantastic_styling,
+                )
+                .await?;
             }
         };
+        Ok(())
+    }
+}
+
+// "progress_dashboard" => "ProgressDashboard", a valid React component name or empty
+fn page_component_name(page: &str) -> String {
+    let component: String = page
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect();
+
+    match component.chars().next() {
+        Some(first) if first.is_ascii_digit() => format!("Page{}", component),
+        _ => component,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_page_components_follow_the_build_sheet() {
+        let pages: Vec<String> = vec![
+            "home_page".to_string(),
+            "progress dashboard".to_string(),
+            "404".to_string(),
+            "Logo".to_string(),
+            "home-page".to_string(),
+        ];
+
+        let waves: Vec<Vec<BuildComponent>> = BuildComponent::build_waves(&pages);
+        assert_eq!(waves[0], vec![BuildComponent::ReactHook]);
+        assert_eq!(waves[1].len(), 3 + pages.len());
+
+        let names: Vec<String> = BuildComponent::pages(&pages)
+            .iter()
+            .map(|component| component.name())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "HomePage",
+                "ProgressDashboard",
+                "Page404",
+                "LogoPage4",
+                "HomePagePage5"
+            ]
+        );
+        assert_eq!(
+            waves[1][4].filepath(),
+            "/src/components/pages/ProgressDashboard.tsx"
+        );
+
+        let build_output: &str = "$ tsc && vite build
+src/components/pages/ProgressDashboard.tsx(12,5): error TS2304: Cannot find name 'progress'.
+src/components/shared/Logo.tsx(3,1): error TS1005: '}' expected.
+  The parser expected to find a '}' to match the '{' token here.
+src/components/pages/ProgressDashboard.tsx(30,9): error TS2322: Type 'string' is not assignable to type 'number'.
+error Command failed with exit code 2.";
+        assert_eq!(
+            BuildComponent::Logo.build_errors(build_output),
+            Some("src/components/shared/Logo.tsx(3,1): error TS1005: '}' expected.\n  The parser expected to find a '}' to match the '{' token here.".to_string())
+        );
+        assert_eq!(
+            waves[1][4]
+                .build_errors(build_output)
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+        assert!(BuildComponent::NavFooter
+            .build_errors(build_output)
+            .is_none());
     }
 }
@@ -0,0 +1,201 @@
+use crate::helpers::utils::save_frontend_code;
+use crate::helpers::workspace::Workspace;
+use crate::models::agents::agent_frontend_comp::BuildComponent;
+use crate::models::general::errors::AgentError;
+use serde::{Deserialize, Serialize};
+
+// Generated without the LLM, so the navigation and the router can never disagree
+const NAV_LINKS_PATH: &str = "/src/navLinks.ts";
+const CONTROLLER_PATH: &str = "/src/components/Controller.tsx";
+
+// One routed page of the website
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PageRoute {
+    pub page: String,
+    pub component: String,
+    pub path: String,
+    pub label: String,
+}
+
+// The first page is the home page, every other page is routed under its component name
+pub fn page_routes(pages: &[String]) -> Vec<PageRoute> {
+    let mut routes: Vec<PageRoute> = vec![];
+    for component in BuildComponent::pages(pages) {
+        if let BuildComponent::Page { index, component } = component {
+            let page: String = pages[index].clone();
+            let path: String = match index {
+                0 => "/".to_string(),
+                _ => format!("/{}", kebab_case(&component)),
+            };
+            let label: String = match title_case(&page) {
+                label if label.is_empty() => component.clone(),
+                label => label,
+            };
+            routes.push(PageRoute {
+                page,
+                component,
+                path,
+                label,
+            });
+        }
+    }
+    routes
+}
+
+// "ProgressDashboard" => "progress-dashboard"
+fn kebab_case(component: &str) -> String {
+    let mut path: String = String::new();
+    for (index, c) in component.chars().enumerate() {
+        if c.is_ascii_uppercase() && index > 0 {
+            path.push('-');
+        }
+        path.push(c.to_ascii_lowercase());
+    }
+    path
+}
+
+// "progress_dashboard" => "Progress Dashboard"
+fn title_case(page: &str) -> String {
+    page.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn js_string(value: &str) -> String {
+    serde_json::Value::String(value.to_string()).to_string()
+}
+
+// src/navLinks.ts -- imported by the Navigation and Footer components
+pub fn nav_links_code(routes: &[PageRoute]) -> String {
+    let links: String = routes
+        .iter()
+        .map(|route| {
+            format!(
+                "  {{ label: {}, path: {} }},\n",
+                js_string(&route.label),
+                js_string(&route.path)
+            )
+        })
+        .collect();
+
+    format!(
+        "export type NavLinkItem = {{ label: string; path: string }};
+
+export const navLinks: NavLinkItem[] = [
+{}];
+",
+        links
+    )
+}
+
+// src/components/Controller.tsx -- the route table of the website
+pub fn controller_code(routes: &[PageRoute]) -> String {
+    let imports: String = routes
+        .iter()
+        .map(|route| {
+            format!(
+                "import {} from \"./pages/{}\";\n",
+                route.component, route.component
+            )
+        })
+        .collect();
+
+    let mut route_table: String = routes
+        .iter()
+        .map(|route| {
+            format!(
+                "        <Route path={} element={{<{} />}} />\n",
+                js_string(&route.path),
+                route.component
+            )
+        })
+        .collect();
+    // Unknown paths go home, once there is a home page to go to
+    if !routes.is_empty() {
+        route_table
+            .push_str("        <Route path=\"*\" element={<Navigate to=\"/\" replace />} />\n");
+    }
+
+    format!(
+        "import {{ BrowserRouter, Navigate, Route, Routes }} from \"react-router-dom\";
+import Navigation from \"./shared/Navigation\";
+import Footer from \"./shared/Footer\";
+{}
+function Controller() {{
+  return (
+    <BrowserRouter>
+      <Navigation />
+      <Routes>
+{}      </Routes>
+      <Footer />
+    </BrowserRouter>
+  );
+}}
+
+export default Controller;
+",
+        imports, route_table
+    )
+}
+
+pub fn save_nav_links(workspace: &Workspace, routes: &[PageRoute]) -> Result<(), AgentError> {
+    save_frontend_code(
+        workspace,
+        &NAV_LINKS_PATH.to_string(),
+        &nav_links_code(routes),
+    )
+}
+
+pub fn save_controller(workspace: &Workspace, routes: &[PageRoute]) -> Result<(), AgentError> {
+    save_frontend_code(
+        workspace,
+        &CONTROLLER_PATH.to_string(),
+        &controller_code(routes),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_routes_and_nav_links_agree() {
+        let pages: Vec<String> = vec![
+            "home_page".to_string(),
+            "progress dashboard".to_string(),
+            "404".to_string(),
+        ];
+
+        let routes: Vec<PageRoute> = page_routes(&pages);
+        let paths: Vec<&str> = routes.iter().map(|route| route.path.as_str()).collect();
+        assert_eq!(paths, vec!["/", "/progress-dashboard", "/page404"]);
+        assert_eq!(routes[1].label, "Progress Dashboard");
+
+        let nav_links: String = nav_links_code(&routes);
+        let controller: String = controller_code(&routes);
+        for route in &routes {
+            let path: String = js_string(&route.path);
+            assert!(nav_links.contains(&format!("path: {}", path)));
+            assert!(controller.contains(&format!(
+                "<Route path={} element={{<{} />}} />",
+                path, route.component
+            )));
+            assert!(controller.contains(&format!(
+                "import {} from \"./pages/{}\";",
+                route.component, route.component
+            )));
+        }
+        assert!(controller.contains("<Navigate to=\"/\" replace />"));
+
+        // No pages yet: nothing to redirect to
+        assert!(!controller_code(&[]).contains("Navigate to"));
+    }
+}
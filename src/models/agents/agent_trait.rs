@@ -2,6 +2,10 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
+use crate::models::agent_basic::basic_agent::AgentState;
+use crate::models::agents::agent_frontend::DesignBuildSheet;
+use crate::models::general::errors::AgentError;
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub struct ProjectScope {
     pub is_crud_required: bool,
@@ -9,15 +13,25 @@ pub struct ProjectScope {
     pub is_external_urls_required: bool,
 }
 
+// Bump when RouteObject changes shape, so stale schema files are rejected instead of misread
+pub const API_SCHEMA_VERSION: u32 = 1;
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RouteObject {
     pub route: String,
-    pub method: String,
     pub is_route_dynamic: bool,
+    pub method: String,
     pub request_body: serde_json::Value,
     pub response: serde_json::Value,
 }
 
+// On-disk form of FactSheet.api_endpoint_schema, see save_api_endpoints
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ApiSchemaFile {
+    pub schema_version: u32,
+    pub endpoints: Vec<RouteObject>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct FactSheet {
     pub project_description: String,
@@ -27,13 +41,80 @@ pub struct FactSheet {
     pub api_endpoint_schema: Option<Vec<RouteObject>>,
 }
 
+// The fact sheet fields an agent can read or write, see FactSheetAccess
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FactSheetField {
+    ProjectDescription,
+    ProjectScope,
+    ExternalUrls,
+    BackendCode,
+    ApiEndpointSchema,
+}
+
+impl FactSheetField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ProjectDescription => "project_description",
+            Self::ProjectScope => "project_scope",
+            Self::ExternalUrls => "external_urls",
+            Self::BackendCode => "backend_code",
+            Self::ApiEndpointSchema => "api_endpoint_schema",
+        }
+    }
+
+    pub fn is_set(&self, fact_sheet: &FactSheet) -> bool {
+        match self {
+            Self::ProjectDescription => !fact_sheet.project_description.is_empty(),
+            Self::ProjectScope => fact_sheet.project_scope.is_some(),
+            Self::ExternalUrls => fact_sheet.external_urls.is_some(),
+            Self::BackendCode => fact_sheet.backend_code.is_some(),
+            Self::ApiEndpointSchema => fact_sheet.api_endpoint_schema.is_some(),
+        }
+    }
+
+    // Agents running at the same time each work on their own copy of the fact sheet
+    pub fn copy(&self, from: &FactSheet, to: &mut FactSheet) {
+        match self {
+            Self::ProjectDescription => to.project_description = from.project_description.clone(),
+            Self::ProjectScope => to.project_scope = from.project_scope,
+            Self::ExternalUrls => to.external_urls = from.external_urls.clone(),
+            Self::BackendCode => to.backend_code = from.backend_code.clone(),
+            Self::ApiEndpointSchema => to.api_endpoint_schema = from.api_endpoint_schema.clone(),
+        }
+    }
+}
+
+// What an agent needs from the fact sheet and what it adds, used by the manager to order agents
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FactSheetAccess {
+    // The agent cannot run without these
+    pub reads: Vec<FactSheetField>,
+    // Used when present, so the agent waits for any agent writing them
+    pub optional_reads: Vec<FactSheetField>,
+    pub writes: Vec<FactSheetField>,
+}
+
+// What a run checkpoint records about a single agent
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentCheckpoint {
+    pub position: String,
+    pub state: AgentState,
+    pub design_build_sheet: Option<DesignBuildSheet>,
+}
+
 #[async_trait]
-pub trait SpecialFunctions: Debug {
+pub trait SpecialFunctions: Debug + Send {
     // Used so that manager can get an agent's position -- useful for debugging
     fn get_agent_position(&mut self) -> String;
 
-    async fn execute(
-        &mut self,
-        fact_sheet: &mut FactSheet,
-    ) -> Result<(), Box<dyn std::error::Error>>;
+    // Only the declared writes are kept from the agent's copy of the fact sheet
+    fn fact_sheet_access(&self) -> FactSheetAccess;
+
+    // Snapshot written to the run checkpoint after the agent has run
+    fn checkpoint(&self) -> AgentCheckpoint;
+
+    // Picks up from a checkpoint -- only work that was fully paid for is skipped
+    fn restore(&mut self, checkpoint: &AgentCheckpoint);
+
+    async fn execute(&mut self, fact_sheet: &mut FactSheet) -> Result<(), AgentError>;
 }
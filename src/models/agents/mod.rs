@@ -1,5 +1,7 @@
 pub mod agent_architect;
 pub mod agent_backend;
+pub mod agent_backend_harness;
 pub mod agent_frontend;
 pub mod agent_frontend_comp;
+pub mod agent_frontend_router;
 pub mod agent_trait;
\ No newline at end of file
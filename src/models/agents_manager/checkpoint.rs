@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::helpers::{command_line::PrintCommand, utils::read_api_endpoints, workspace::Workspace};
+use crate::models::{
+    agents::agent_trait::{AgentCheckpoint, FactSheet},
+    general::errors::AgentError,
+};
+
+// Everything needed to pick a run back up without paying for finished agents again
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Checkpoint {
+    pub fact_sheet: FactSheet,
+    pub agents: Vec<AgentCheckpoint>,
+    // Missing in checkpoints written before runs had their own workspace
+    #[serde(default)]
+    pub workspace: Option<Workspace>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Self, AgentError> {
+        let contents: String = fs::read_to_string(path)?;
+        let checkpoint: Self = serde_json::from_str(&contents).map_err(|e| {
+            AgentError::Decode(format!("Invalid checkpoint file {}: {}", path.display(), e))
+        })?;
+        Ok(checkpoint)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let contents: String = serde_json::to_string_pretty(self)?;
+        Ok(fs::write(path, contents)?)
+    }
+
+    pub fn print(&self) {
+        let position: &str = "Checkpoint";
+        let fact_sheet: &FactSheet = &self.fact_sheet;
+
+        PrintCommand::AICall.print_agent_message(
+            position,
+            format!("Project: {}", fact_sheet.project_description).as_str(),
+        );
+        PrintCommand::AICall.print_agent_message(
+            position,
+            format!("Scope: {:?}", fact_sheet.project_scope).as_str(),
+        );
+        PrintCommand::AICall.print_agent_message(
+            position,
+            format!("External urls: {:?}", fact_sheet.external_urls).as_str(),
+        );
+        if let Some(workspace) = &self.workspace {
+            PrintCommand::AICall.print_agent_message(
+                position,
+                format!("Workspace: {}", workspace.root.display()).as_str(),
+            );
+            // The schema file should always match the fact sheet, flag it when it does not
+            match read_api_endpoints(workspace) {
+                Ok(endpoints) if Some(&endpoints) == fact_sheet.api_endpoint_schema.as_ref() => {}
+                Ok(endpoints) => PrintCommand::Issue.print_agent_message(
+                    position,
+                    format!(
+                        "Workspace API schema has {} endpoints and does not match the fact sheet",
+                        endpoints.len()
+                    )
+                    .as_str(),
+                ),
+                Err(e) => PrintCommand::Issue.print_agent_message(
+                    position,
+                    format!("Workspace API schema unreadable -- {}", e).as_str(),
+                ),
+            }
+        }
+        PrintCommand::AICall.print_agent_message(
+            position,
+            format!(
+                "Backend code: {}, API endpoints: {}",
+                match &fact_sheet.backend_code {
+                    Some(code) => format!("{} lines", code.lines().count()),
+                    None => "none".to_string(),
+                },
+                fact_sheet
+                    .api_endpoint_schema
+                    .as_ref()
+                    .map_or(0, |endpoints| endpoints.len())
+            )
+            .as_str(),
+        );
+
+        for agent in &self.agents {
+            PrintCommand::UnitTest.print_agent_message(
+                &agent.position,
+                format!("State: {:?}", agent.state).as_str(),
+            );
+            if let Some(build_sheet) = &agent.design_build_sheet {
+                PrintCommand::UnitTest.print_agent_message(
+                    &agent.position,
+                    format!("Pages: {:?}", build_sheet.pages).as_str(),
+                );
+            }
+        }
+    }
+}
+
+// Accepts either a bare fact sheet (i.e. one saved when the budget ran out) or a whole checkpoint
+pub fn load_fact_sheet(path: &Path) -> Result<FactSheet, AgentError> {
+    let contents: String = fs::read_to_string(path)?;
+    if let Ok(checkpoint) = serde_json::from_str::<Checkpoint>(&contents) {
+        return Ok(checkpoint.fact_sheet);
+    }
+
+    serde_json::from_str::<FactSheet>(&contents).map_err(|e| {
+        AgentError::Decode(format!("Invalid fact sheet file {}: {}", path.display(), e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::agent_basic::basic_agent::AgentState;
+
+    #[test]
+    fn tests_checkpoint_round_trip() {
+        let checkpoint: Checkpoint = Checkpoint {
+            fact_sheet: FactSheet {
+                project_description: "build a website that shows cat pictures".to_string(),
+                project_scope: None,
+                external_urls: Some(vec![
+                    "https://api.thecatapi.com/v1/images/search".to_string()
+                ]),
+                backend_code: None,
+                api_endpoint_schema: None,
+            },
+            agents: vec![AgentCheckpoint {
+                position: "Solutions Architect".to_string(),
+                state: AgentState::Finished,
+                design_build_sheet: None,
+            }],
+            workspace: Some(Workspace::open(
+                std::env::temp_dir().join("auto_gpt_tests_run"),
+            )),
+        };
+
+        let path = std::env::temp_dir().join("auto_gpt_tests_checkpoint_round_trip.json");
+        checkpoint.save(&path).unwrap();
+        let loaded: Checkpoint = Checkpoint::load(&path).unwrap();
+
+        assert_eq!(loaded.fact_sheet, checkpoint.fact_sheet);
+        assert_eq!(load_fact_sheet(&path).unwrap(), checkpoint.fact_sheet);
+        assert_eq!(loaded.agents.len(), 1);
+        assert_eq!(loaded.agents[0].position, "Solutions Architect");
+        assert_eq!(loaded.agents[0].state, AgentState::Finished);
+        assert_eq!(loaded.workspace, checkpoint.workspace);
+        let _ = fs::remove_file(&path);
+    }
+}
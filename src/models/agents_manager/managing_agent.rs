@@ -1,47 +1,146 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::task::{self, JoinError, JoinHandle, JoinSet};
+
 use crate::{
     ai_functions::managing::convert_user_input_to_goal,
-    helpers::utils::ai_task_request,
+    apis::{
+        llm_provider::LlmProvider,
+        retry_policy::{RetryPolicy, RetryingProvider},
+        usage::usage_snapshot,
+    },
+    helpers::{
+        command_line::{with_stream_echo, PrintCommand},
+        utils::ai_task_request,
+        workspace::Workspace,
+    },
     models::{
-        agent_basic::{
-            basic_agent::{AgentState, BasicAgent},
-            basic_traits::BasicTraits,
-        },
+        agent_basic::basic_agent::{AgentState, BasicAgent},
         agents::{
             agent_architect::AgentSolutionArchitect,
             agent_backend::AgentBackendDeveloper,
             agent_frontend::AgentFrontendDeveloper,
-            agent_trait::{FactSheet, SpecialFunctions},
+            agent_trait::{AgentCheckpoint, FactSheet, SpecialFunctions},
+        },
+        agents_manager::{
+            checkpoint::Checkpoint,
+            scheduler::{dependency_graph, unmet_prerequisites, ScheduledAgent},
         },
+        general::errors::AgentError,
     },
 };
 
+// The agent and its copy of the fact sheet once it has run
+type AgentExecution = (Box<dyn SpecialFunctions>, FactSheet, Result<(), AgentError>);
+
+// A finished agent task: the agent's index, and Err when the agent panicked and took its copy of
+// the fact sheet with it
+type AgentRun = (usize, Result<AgentExecution, JoinError>);
+
+// Which agents a run includes, see the plan-only / backend-only / frontend-only subcommands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AgentSelection {
+    All,
+    PlanOnly,
+    BackendOnly,
+    FrontendOnly,
+}
+
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    pub agents: AgentSelection,
+    // Agents generate and build code in here, never in the templates. The checkpoint and any
+    // saved fact sheet are written here too
+    pub workspace: Workspace,
+    // Where the generated server is documented as running, see --server-url
+    pub server_url: String,
+}
+
+impl RunConfig {
+    pub fn new(agents: AgentSelection, workspace: Workspace, server_url: String) -> Self {
+        Self {
+            agents,
+            workspace,
+            server_url,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AgentOutcome {
+    Completed,
+    // Finished in the checkpoint this run was resumed from
+    Resumed,
+    Failed(AgentError),
+    // Not run because an agent it depends on failed, or the run could not be scheduled
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct AgentReport {
+    pub position: String,
+    pub outcome: AgentOutcome,
+}
+
+// Structured summary of a managed run, returned instead of aborting on the first failure
+#[derive(Debug)]
+pub struct RunReport {
+    pub agents: Vec<AgentReport>,
+}
+
+impl RunReport {
+    pub fn is_success(&self) -> bool {
+        self.agents.iter().all(|report| {
+            matches!(
+                report.outcome,
+                AgentOutcome::Completed | AgentOutcome::Resumed
+            )
+        })
+    }
+
+    pub fn print(&self) {
+        for report in &self.agents {
+            match &report.outcome {
+                AgentOutcome::Completed => {
+                    PrintCommand::UnitTest.print_agent_message(&report.position, "Completed")
+                }
+                AgentOutcome::Resumed => PrintCommand::UnitTest
+                    .print_agent_message(&report.position, "Completed in an earlier run"),
+                AgentOutcome::Failed(e) => PrintCommand::Issue
+                    .print_agent_message(&report.position, format!("Failed -- {}", e).as_str()),
+                AgentOutcome::Skipped => PrintCommand::Issue
+                    .print_agent_message(&report.position, "Skipped after another agent failed"),
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ManagingAgent {
     attributes: BasicAgent,
     fact_sheet: FactSheet,
     agents: Vec<Box<dyn SpecialFunctions>>,
+    llm: Arc<dyn LlmProvider>,
+    config: RunConfig,
+    // Agent snapshots from the checkpoint being resumed, applied once the agents are created
+    restored_agents: Vec<AgentCheckpoint>,
 }
 
 impl ManagingAgent {
-    pub async fn new(user_request: String) -> Result<Self, Box<dyn std::error::Error>> {
-        let position: String = "Project Manager".to_string();
-        let attributes: BasicAgent = BasicAgent {
-            objective: "Manage agents who are building an excellent website for a user."
-                .to_string(),
-            position: position.clone(),
-            state: AgentState::Discovery,
-            memory: Vec::new(),
-        };
-
+    pub async fn new(
+        user_request: String,
+        llm: Arc<dyn LlmProvider>,
+        config: RunConfig,
+    ) -> Result<Self, AgentError> {
         let project_description: String = ai_task_request(
+            &RetryingProvider::new(llm.clone(), RetryPolicy::from_env("MANAGER")),
             user_request,
-            &position,
+            "Project Manager",
             get_function_string!(convert_user_input_to_goal),
             convert_user_input_to_goal,
         )
-        .await;
-
-        let agents: Vec<Box<dyn SpecialFunctions>> = vec![];
+        .await?;
 
         let fact_sheet: FactSheet = FactSheet {
             project_description,
@@ -51,54 +150,496 @@ impl ManagingAgent {
             api_endpoint_schema: None,
         };
 
-        return Ok(Self {
+        Ok(Self::from_fact_sheet(fact_sheet, llm, config))
+    }
+
+    // Starts from a fact sheet written by an earlier run (i.e. for frontend-only runs)
+    pub fn from_fact_sheet(
+        fact_sheet: FactSheet,
+        llm: Arc<dyn LlmProvider>,
+        config: RunConfig,
+    ) -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Manage agents who are building an excellent website for a user."
+                .to_string(),
+            position: "Project Manager".to_string(),
+            state: AgentState::Discovery,
+            memory: Vec::new(),
+        };
+
+        Self {
             attributes,
             fact_sheet,
-            agents,
-        });
+            agents: vec![],
+            llm,
+            config,
+            restored_agents: vec![],
+        }
+    }
+
+    // Resumes a run without asking the model for the project goal again
+    pub fn from_checkpoint(
+        checkpoint: Checkpoint,
+        llm: Arc<dyn LlmProvider>,
+        config: RunConfig,
+    ) -> Self {
+        let mut managing_agent: Self = Self::from_fact_sheet(checkpoint.fact_sheet, llm, config);
+        managing_agent.restored_agents = checkpoint.agents;
+        managing_agent
     }
 
-    pub async fn execute_project(&mut self) {
+    pub async fn execute_project(&mut self) -> RunReport {
         self.create_agents();
-        for agent in &mut self.agents {
-            let position: String = agent.get_agent_position();
-            let error_message: String = format!("{} failed to execute", position);
 
-            if let Err(e) = agent.execute(&mut self.fact_sheet).await {
-                panic!("{} -- Error: {}", error_message, e);
+        let scheduled: Vec<ScheduledAgent> = self.scheduled_agents();
+        let mut outcomes: Vec<Option<AgentOutcome>> = self.schedule_outcomes(&scheduled);
+        let dependencies: Vec<Vec<usize>> = match dependency_graph(&scheduled) {
+            Ok(dependencies) => dependencies,
+            // schedule_outcomes has already failed every agent
+            Err(_) => vec![vec![]; scheduled.len()],
+        };
+        let mut snapshots: Vec<AgentCheckpoint> =
+            self.agents.iter().map(|agent| agent.checkpoint()).collect();
+
+        // Each agent runs on its own copy of the fact sheet as soon as the agents it depends on are done
+        let mut agents: Vec<Option<Box<dyn SpecialFunctions>>> =
+            self.agents.drain(..).map(Some).collect();
+        let mut running: JoinSet<AgentRun> = JoinSet::new();
+        loop {
+            let mut ready: Vec<usize> = vec![];
+            for index in 0..agents.len() {
+                if outcomes[index].is_some() || agents[index].is_none() {
+                    continue;
+                }
+                let dependency_outcomes: Vec<&Option<AgentOutcome>> = dependencies[index]
+                    .iter()
+                    .map(|dependency| &outcomes[*dependency])
+                    .collect();
+                let has_failed_dependency: bool = dependency_outcomes.iter().any(|outcome| {
+                    matches!(
+                        outcome,
+                        Some(AgentOutcome::Failed(_)) | Some(AgentOutcome::Skipped)
+                    )
+                });
+                if has_failed_dependency {
+                    outcomes[index] = Some(AgentOutcome::Skipped);
+                    continue;
+                }
+                if dependency_outcomes.iter().all(|outcome| outcome.is_some()) {
+                    ready.push(index);
+                }
+            }
+
+            // Only an agent running on its own streams to the terminal, so tokens never interleave
+            let is_echoed: bool = running.is_empty() && ready.len() == 1;
+            for index in ready {
+                if let Some(agent) = agents[index].take() {
+                    spawn_agent(
+                        &mut running,
+                        index,
+                        agent,
+                        self.fact_sheet.clone(),
+                        is_echoed,
+                    );
+                }
+            }
+
+            let (index, execution): AgentRun = match running.join_next().await {
+                Some(Ok(finished)) => finished,
+                // Only the agent's own task can fail, see spawn_agent
+                Some(Err(_)) => continue,
+                None => break,
+            };
+
+            match execution {
+                Ok((agent, fact_sheet, result)) => {
+                    // Whatever the agent wrote is kept even when it failed, so the run can be resumed
+                    for field in agent.fact_sheet_access().writes {
+                        field.copy(&fact_sheet, &mut self.fact_sheet);
+                    }
+                    outcomes[index] = Some(match result {
+                        Ok(()) => AgentOutcome::Completed,
+                        Err(e) => AgentOutcome::Failed(e),
+                    });
+                    snapshots[index] = agent.checkpoint();
+                    agents[index] = Some(agent);
+                }
+                // The other agents keep running, the checkpoint keeps the snapshot from its start
+                Err(e) => outcomes[index] = Some(AgentOutcome::Failed(AgentError::from(e))),
+            }
+
+            if let Err(e) = self.save_checkpoint(&snapshots) {
+                PrintCommand::Issue.print_agent_message(
+                    &self.attributes.position,
+                    format!("Failed to write checkpoint -- {}", e).as_str(),
+                );
             }
         }
+        self.agents = agents.into_iter().flatten().collect();
+
+        let reports: Vec<AgentReport> = snapshots
+            .into_iter()
+            .zip(outcomes)
+            .map(|(snapshot, outcome)| AgentReport {
+                position: snapshot.position,
+                outcome: outcome.unwrap_or(AgentOutcome::Skipped),
+            })
+            .collect();
+
+        usage_snapshot().print();
+
+        // Keep what was paid for so far, so the run can be picked up again
+        let is_budget_exhausted: bool = reports
+            .iter()
+            .any(|report| matches!(report.outcome, AgentOutcome::Failed(AgentError::Budget(_))));
+        if is_budget_exhausted {
+            let fact_sheet_path: PathBuf = self.config.workspace.fact_sheet_path();
+            match self.save_fact_sheet(&fact_sheet_path) {
+                Ok(()) => PrintCommand::Issue.print_agent_message(
+                    &self.attributes.position,
+                    format!(
+                        "Budget exhausted, fact sheet saved to {}",
+                        fact_sheet_path.display()
+                    )
+                    .as_str(),
+                ),
+                Err(e) => PrintCommand::Issue.print_agent_message(
+                    &self.attributes.position,
+                    format!("Budget exhausted, failed to save fact sheet -- {}", e).as_str(),
+                ),
+            }
+        }
+
+        RunReport { agents: reports }
+    }
+
+    pub fn save_fact_sheet(&self, path: &Path) -> Result<(), AgentError> {
+        let contents: String = serde_json::to_string_pretty(&self.fact_sheet)?;
+        Ok(fs::write(path, contents)?)
+    }
+
+    pub fn workspace(&self) -> &Workspace {
+        &self.config.workspace
+    }
+
+    // Agents still running are saved as they were when they started
+    pub fn save_checkpoint(&self, agents: &[AgentCheckpoint]) -> Result<(), AgentError> {
+        let checkpoint: Checkpoint = Checkpoint {
+            fact_sheet: self.fact_sheet.clone(),
+            agents: agents.to_vec(),
+            workspace: Some(self.config.workspace.clone()),
+        };
+        checkpoint.save(&self.config.workspace.checkpoint_path())
+    }
+
+    fn scheduled_agents(&self) -> Vec<ScheduledAgent> {
+        self.agents
+            .iter()
+            .map(|agent| {
+                let checkpoint: AgentCheckpoint = agent.checkpoint();
+                ScheduledAgent {
+                    position: checkpoint.position,
+                    access: agent.fact_sheet_access(),
+                    is_finished: checkpoint.state == AgentState::Finished,
+                }
+            })
+            .collect()
+    }
+
+    // Outcomes known before anything runs: resumed agents, and every agent when a prerequisite
+    // is missing or the agents cannot be ordered, so no tokens are spent on a run that cannot finish
+    fn schedule_outcomes(&self, scheduled: &[ScheduledAgent]) -> Vec<Option<AgentOutcome>> {
+        let mut outcomes: Vec<Option<AgentOutcome>> = scheduled
+            .iter()
+            .map(|agent| match agent.is_finished {
+                true => Some(AgentOutcome::Resumed),
+                false => None,
+            })
+            .collect();
+
+        let mut unmet: Vec<(usize, AgentError)> = unmet_prerequisites(scheduled, &self.fact_sheet);
+        if let Err(e) = dependency_graph(scheduled) {
+            for (index, agent) in scheduled.iter().enumerate() {
+                if !agent.is_finished {
+                    unmet.push((index, AgentError::Config(e.to_string())));
+                }
+            }
+        }
+        if unmet.is_empty() {
+            return outcomes;
+        }
+
+        for outcome in outcomes.iter_mut().filter(|outcome| outcome.is_none()) {
+            *outcome = Some(AgentOutcome::Skipped);
+        }
+        for (index, e) in unmet {
+            outcomes[index] = Some(AgentOutcome::Failed(e));
+        }
+        outcomes
     }
 
     fn create_agents(&mut self) {
-        self.add_agent(Box::new(AgentSolutionArchitect::new()));
-        self.add_agent(Box::new(AgentBackendDeveloper::new()));
-        // TODO: add FE dev
-        // self.add_agent(Box::new(AgentFrontendDeveloper::new()));
+        let selection: AgentSelection = self.config.agents;
+
+        if matches!(selection, AgentSelection::All | AgentSelection::PlanOnly) {
+            self.add_agent(Box::new(AgentSolutionArchitect::new(
+                self.agent_llm("ARCHITECT"),
+            )));
+        }
+        if matches!(selection, AgentSelection::All | AgentSelection::BackendOnly) {
+            self.add_agent(Box::new(AgentBackendDeveloper::new(
+                self.agent_llm("BACKEND"),
+                self.config.workspace.clone(),
+                self.config.server_url.clone(),
+            )));
+        }
+        if matches!(
+            selection,
+            AgentSelection::All | AgentSelection::FrontendOnly
+        ) {
+            self.add_agent(Box::new(AgentFrontendDeveloper::new(
+                self.agent_llm("FRONTEND"),
+                self.config.workspace.clone(),
+            )));
+        }
     }
 
-    fn add_agent(&mut self, agent: Box<dyn SpecialFunctions>) {
+    // Each agent gets its own retry policy, see RetryPolicy::from_env
+    fn agent_llm(&self, agent: &str) -> Arc<dyn LlmProvider> {
+        Arc::new(RetryingProvider::new(
+            self.llm.clone(),
+            RetryPolicy::from_env(agent),
+        ))
+    }
+
+    fn add_agent(&mut self, mut agent: Box<dyn SpecialFunctions>) {
+        let position: String = agent.get_agent_position();
+        if let Some(checkpoint) = self
+            .restored_agents
+            .iter()
+            .find(|checkpoint| checkpoint.position == position)
+        {
+            agent.restore(checkpoint);
+        }
         self.agents.push(agent);
     }
 }
 
+// The agent runs in a task of its own, so a panic ends only that task and comes back as its
+// JoinError instead of unwinding through the manager and the agents running next to it
+fn spawn_agent(
+    running: &mut JoinSet<AgentRun>,
+    index: usize,
+    mut agent: Box<dyn SpecialFunctions>,
+    mut fact_sheet: FactSheet,
+    is_echoed: bool,
+) {
+    let execution: JoinHandle<AgentExecution> =
+        task::spawn(with_stream_echo(is_echoed, async move {
+            let result: Result<(), AgentError> = agent.execute(&mut fact_sheet).await;
+            (agent, fact_sheet, result)
+        }));
+    running.spawn(async move { (index, execution.await) });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::apis::replay_provider::ReplayProvider;
+    use crate::apis::scripted_provider::ScriptedProvider;
+    use crate::helpers::command_line::set_allow_unreviewed_code;
+    use crate::helpers::openapi::DEFAULT_SERVER_URL;
+    use crate::helpers::workspace::test_workspace;
+    use crate::models::agents::agent_backend::tests::{SERVER_CODE, SERVER_ROUTE_BODIES};
+    use crate::models::agents::agent_trait::FactSheetAccess;
 
     #[tokio::test]
     async fn tests_managing_agent() {
+        // The frontend has no scripted answers, so it fails on its first call: the run is checked
+        // up to the backend
+        let llm: Arc<dyn LlmProvider> = Arc::new(ScriptedProvider::new(&[
+            (
+                "convert_user_input_to_goal",
+                "build a website that shows random pictures of cats",
+            ),
+            (
+                "print_project_scope",
+                "{\"is_crud_required\":false,\"is_user_login_and_logout\":false,\"is_external_urls_required\":true}",
+            ),
+            ("print_site_urls", "[]"),
+            ("print_backend_webserver_code", SERVER_CODE),
+            ("print_improved_webserver_code", SERVER_CODE),
+            ("print_rest_api_endpoint_bodies", SERVER_ROUTE_BODIES),
+        ]));
+        set_allow_unreviewed_code(true);
         let user_request: &str =
             "Build me a website that randomly shows cool pictures of cats. I should query an endpoint, and retrieve a random image from a cat fetched from an external API!";
-        let mut agent: ManagingAgent = ManagingAgent::new(user_request.to_string())
+        let (_output_dir, workspace): (tempfile::TempDir, Workspace) = test_workspace();
+        let config: RunConfig = RunConfig::new(
+            AgentSelection::All,
+            workspace,
+            DEFAULT_SERVER_URL.to_string(),
+        );
+        let mut agent: ManagingAgent = ManagingAgent::new(user_request.to_string(), llm, config)
             .await
             .expect("Error creating Managing Agent");
 
-        agent.execute_project().await;
+        let report: RunReport = agent.execute_project().await;
+        report.print();
 
+        let outcome = |position: &str| {
+            &report
+                .agents
+                .iter()
+                .find(|report| report.position == position)
+                .unwrap()
+                .outcome
+        };
+        assert!(matches!(
+            outcome("Solutions Architect"),
+            AgentOutcome::Completed
+        ));
+        assert!(matches!(
+            outcome("Backend Developer"),
+            AgentOutcome::Completed
+        ));
         assert!(agent.fact_sheet.project_scope.is_some());
-        assert!(agent.fact_sheet.external_urls.is_some());
+        assert_eq!(agent.fact_sheet.external_urls, Some(vec![]));
+        assert_eq!(agent.fact_sheet.backend_code.as_deref(), Some(SERVER_CODE));
+        assert!(agent.fact_sheet.api_endpoint_schema.is_some());
+
+        let checkpoint: Checkpoint =
+            Checkpoint::load(&agent.workspace().checkpoint_path()).unwrap();
+        assert_eq!(checkpoint.fact_sheet, agent.fact_sheet);
+        assert_eq!(checkpoint.agents[0].position, "Solutions Architect");
+        assert_eq!(checkpoint.agents[0].state, AgentState::Finished);
+        assert_eq!(checkpoint.workspace, Some(agent.config.workspace.clone()));
+    }
+
+    #[tokio::test]
+    async fn tests_resume_skips_completed_agents() {
+        let finished = |position: &str| AgentCheckpoint {
+            position: position.to_string(),
+            state: AgentState::Finished,
+            design_build_sheet: None,
+        };
+        let checkpoint: Checkpoint = Checkpoint {
+            fact_sheet: FactSheet {
+                project_description: "build a website that shows cat pictures".to_string(),
+                project_scope: None,
+                external_urls: None,
+                backend_code: None,
+                api_endpoint_schema: None,
+            },
+            agents: vec![
+                finished("Solutions Architect"),
+                finished("Backend Developer"),
+                finished("Frontend Developer"),
+            ],
+            workspace: None,
+        };
+
+        // No fixtures: any LLM call would fail the run
+        let llm: Arc<dyn LlmProvider> = Arc::new(ReplayProvider::replay(
+            std::env::temp_dir().join("auto_gpt_tests_resume_no_fixtures"),
+        ));
+        let (_output_dir, workspace): (tempfile::TempDir, Workspace) = test_workspace();
+        let config: RunConfig = RunConfig::new(
+            AgentSelection::All,
+            workspace,
+            DEFAULT_SERVER_URL.to_string(),
+        );
+        let mut agent: ManagingAgent = ManagingAgent::from_checkpoint(checkpoint, llm, config);
+
+        let report: RunReport = agent.execute_project().await;
+        assert!(report.is_success());
+        assert!(report
+            .agents
+            .iter()
+            .all(|report| matches!(report.outcome, AgentOutcome::Resumed)));
+    }
 
-        dbg!(agent.fact_sheet);
+    #[tokio::test]
+    async fn tests_unmet_prerequisites_fail_before_any_call() {
+        let fact_sheet: FactSheet = FactSheet {
+            project_description: "build a website that shows cat pictures".to_string(),
+            project_scope: None,
+            external_urls: None,
+            backend_code: None,
+            api_endpoint_schema: None,
+        };
+
+        // No fixtures: any LLM call would fail with a transport error instead
+        let llm: Arc<dyn LlmProvider> = Arc::new(ReplayProvider::replay(
+            std::env::temp_dir().join("auto_gpt_tests_prerequisites_no_fixtures"),
+        ));
+        let (_output_dir, workspace): (tempfile::TempDir, Workspace) = test_workspace();
+        let config: RunConfig = RunConfig::new(
+            AgentSelection::FrontendOnly,
+            workspace,
+            DEFAULT_SERVER_URL.to_string(),
+        );
+        let mut agent: ManagingAgent = ManagingAgent::from_fact_sheet(fact_sheet, llm, config);
+
+        let report: RunReport = agent.execute_project().await;
+        assert!(!report.is_success());
+        match &report.agents[0].outcome {
+            AgentOutcome::Failed(AgentError::MissingInput(message)) => {
+                assert!(message.contains("api_endpoint_schema"))
+            }
+            outcome => panic!("expected a missing input failure, got {:?}", outcome),
+        }
+        assert!(!agent.workspace().checkpoint_path().exists());
+    }
+    #[derive(Debug)]
+    struct PanickingAgent;
+
+    #[async_trait::async_trait]
+    impl SpecialFunctions for PanickingAgent {
+        fn get_agent_position(&mut self) -> String {
+            "Panicking Agent".to_string()
+        }
+
+        fn fact_sheet_access(&self) -> FactSheetAccess {
+            FactSheetAccess {
+                reads: vec![],
+                optional_reads: vec![],
+                writes: vec![],
+            }
+        }
+
+        fn checkpoint(&self) -> AgentCheckpoint {
+            AgentCheckpoint {
+                position: "Panicking Agent".to_string(),
+                state: AgentState::Discovery,
+                design_build_sheet: None,
+            }
+        }
+
+        fn restore(&mut self, _checkpoint: &AgentCheckpoint) {}
+
+        async fn execute(&mut self, _fact_sheet: &mut FactSheet) -> Result<(), AgentError> {
+            panic!("bug in the agent")
+        }
+    }
+
+    #[tokio::test]
+    async fn tests_a_panicking_agent_fails_on_its_own() {
+        let fact_sheet: FactSheet = FactSheet {
+            project_description: "build a website that shows cat pictures".to_string(),
+            project_scope: None,
+            external_urls: None,
+            backend_code: None,
+            api_endpoint_schema: None,
+        };
+        let mut running: JoinSet<AgentRun> = JoinSet::new();
+        spawn_agent(&mut running, 2, Box::new(PanickingAgent), fact_sheet, false);
+
+        let (index, execution): AgentRun = running.join_next().await.unwrap().unwrap();
+        assert_eq!(index, 2);
+        match execution.map_err(AgentError::from) {
+            Err(AgentError::Task(message)) => assert!(message.contains("panicked")),
+            execution => panic!("expected a task failure, got {:?}", execution),
+        }
     }
 }
@@ -1 +1,3 @@
-pub mod managing_agent;
\ No newline at end of file
+pub mod checkpoint;
+pub mod managing_agent;
+pub mod scheduler;
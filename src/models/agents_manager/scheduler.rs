@@ -0,0 +1,216 @@
+use crate::models::{
+    agents::agent_trait::{FactSheet, FactSheetAccess, FactSheetField},
+    general::errors::AgentError,
+};
+
+// An agent as the scheduler sees it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledAgent {
+    pub position: String,
+    pub access: FactSheetAccess,
+    // Finished in the checkpoint being resumed, so it will not run
+    pub is_finished: bool,
+}
+
+impl ScheduledAgent {
+    fn needs(&self, field: &FactSheetField) -> bool {
+        self.access.reads.contains(field) || self.access.optional_reads.contains(field)
+    }
+
+    fn writes_any(&self, fields: &[FactSheetField]) -> bool {
+        self.access
+            .writes
+            .iter()
+            .any(|field| fields.contains(field))
+    }
+}
+
+// Required fields that are not in the fact sheet and that no agent left to run writes,
+// as (agent index, error) -- checked before any tokens are spent
+pub fn unmet_prerequisites(
+    agents: &[ScheduledAgent],
+    fact_sheet: &FactSheet,
+) -> Vec<(usize, AgentError)> {
+    let mut unmet: Vec<(usize, AgentError)> = vec![];
+    for (index, agent) in agents.iter().enumerate() {
+        if agent.is_finished {
+            continue;
+        }
+        let missing: Vec<&str> = agent
+            .access
+            .reads
+            .iter()
+            .filter(|field| !field.is_set(fact_sheet))
+            .filter(|field| {
+                !agents.iter().enumerate().any(|(writer_index, writer)| {
+                    writer_index != index
+                        && !writer.is_finished
+                        && writer.access.writes.contains(field)
+                })
+            })
+            .map(|field| field.name())
+            .collect();
+        if !missing.is_empty() {
+            unmet.push((
+                index,
+                AgentError::MissingInput(format!(
+                    "{} needs {} in the fact sheet and no agent in this run writes it",
+                    agent.position,
+                    missing.join(", ")
+                )),
+            ));
+        }
+    }
+    unmet
+}
+
+// For every agent, the agents that must finish before it starts. An agent waits for whoever writes
+// a field it reads, and for earlier agents writing the same fields as it does
+pub fn dependency_graph(agents: &[ScheduledAgent]) -> Result<Vec<Vec<usize>>, AgentError> {
+    let mut dependencies: Vec<Vec<usize>> = vec![vec![]; agents.len()];
+    for (index, agent) in agents.iter().enumerate() {
+        if agent.is_finished {
+            continue;
+        }
+        for (other_index, other) in agents.iter().enumerate() {
+            if other_index == index || other.is_finished {
+                continue;
+            }
+            let is_reading_its_output: bool =
+                other.access.writes.iter().any(|field| agent.needs(field));
+            let is_earlier_writer: bool =
+                other_index < index && other.writes_any(&agent.access.writes);
+            if is_reading_its_output || is_earlier_writer {
+                dependencies[index].push(other_index);
+            }
+        }
+    }
+
+    // Kahn's algorithm: whatever cannot be ordered is part of a cycle
+    let mut is_ordered: Vec<bool> = vec![false; agents.len()];
+    loop {
+        let ready: Vec<usize> = (0..agents.len())
+            .filter(|index| !is_ordered[*index])
+            .filter(|index| {
+                dependencies[*index]
+                    .iter()
+                    .all(|dependency| is_ordered[*dependency])
+            })
+            .collect();
+        if ready.is_empty() {
+            break;
+        }
+        for index in ready {
+            is_ordered[index] = true;
+        }
+    }
+    let cycle: Vec<&str> = agents
+        .iter()
+        .zip(is_ordered.iter())
+        .filter(|(_, is_ordered)| !**is_ordered)
+        .map(|(agent, _)| agent.position.as_str())
+        .collect();
+    if !cycle.is_empty() {
+        return Err(AgentError::Config(format!(
+            "Agents depend on each other's fact sheet fields: {}",
+            cycle.join(", ")
+        )));
+    }
+
+    Ok(dependencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(
+        position: &str,
+        reads: Vec<FactSheetField>,
+        optional_reads: Vec<FactSheetField>,
+        writes: Vec<FactSheetField>,
+    ) -> ScheduledAgent {
+        ScheduledAgent {
+            position: position.to_string(),
+            access: FactSheetAccess {
+                reads,
+                optional_reads,
+                writes,
+            },
+            is_finished: false,
+        }
+    }
+
+    #[test]
+    fn tests_agents_are_scheduled_from_their_fact_sheet_fields() {
+        let fact_sheet: FactSheet = FactSheet {
+            project_description: "build a website that shows cat pictures".to_string(),
+            project_scope: None,
+            external_urls: None,
+            backend_code: None,
+            api_endpoint_schema: None,
+        };
+        let architect: ScheduledAgent = agent(
+            "Solutions Architect",
+            vec![FactSheetField::ProjectDescription],
+            vec![],
+            vec![FactSheetField::ProjectScope, FactSheetField::ExternalUrls],
+        );
+        let backend: ScheduledAgent = agent(
+            "Backend Developer",
+            vec![FactSheetField::ProjectDescription],
+            vec![],
+            vec![FactSheetField::ApiEndpointSchema],
+        );
+        let frontend: ScheduledAgent = agent(
+            "Frontend Developer",
+            vec![FactSheetField::ApiEndpointSchema],
+            vec![FactSheetField::ExternalUrls],
+            vec![],
+        );
+
+        // The architect and backend developer are independent, the frontend waits for both
+        let agents: Vec<ScheduledAgent> =
+            vec![architect.clone(), backend.clone(), frontend.clone()];
+        assert!(unmet_prerequisites(&agents, &fact_sheet).is_empty());
+        assert_eq!(
+            dependency_graph(&agents).unwrap(),
+            vec![vec![], vec![], vec![0, 1]]
+        );
+
+        // Without the backend developer nothing writes the API schema
+        let agents: Vec<ScheduledAgent> = vec![architect.clone(), frontend.clone()];
+        let unmet: Vec<(usize, AgentError)> = unmet_prerequisites(&agents, &fact_sheet);
+        assert_eq!(unmet.len(), 1);
+        assert_eq!(unmet[0].0, 1);
+        assert!(unmet[0].1.to_string().contains("api_endpoint_schema"));
+
+        // Finished agents neither run nor hold anyone up
+        let mut finished_backend: ScheduledAgent = backend.clone();
+        finished_backend.is_finished = true;
+        let with_schema: FactSheet = FactSheet {
+            api_endpoint_schema: Some(vec![]),
+            ..fact_sheet.clone()
+        };
+        let agents: Vec<ScheduledAgent> = vec![architect.clone(), finished_backend, frontend];
+        assert!(unmet_prerequisites(&agents, &with_schema).is_empty());
+        assert_eq!(
+            dependency_graph(&agents).unwrap(),
+            vec![vec![], vec![], vec![0]]
+        );
+
+        let reviewer: ScheduledAgent = agent(
+            "Reviewer",
+            vec![FactSheetField::ProjectScope],
+            vec![],
+            vec![FactSheetField::ApiEndpointSchema],
+        );
+        let planner: ScheduledAgent = agent(
+            "Planner",
+            vec![FactSheetField::ApiEndpointSchema],
+            vec![],
+            vec![FactSheetField::ProjectScope],
+        );
+        assert!(dependency_graph(&[reviewer, planner]).is_err());
+    }
+}
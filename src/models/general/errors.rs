@@ -0,0 +1,101 @@
+use std::fmt;
+use std::time::Duration;
+
+// Every way an agent (or the LLM call path beneath it) can fail
+#[derive(Debug)]
+pub enum AgentError {
+    // Missing or invalid configuration (i.e. environment variables)
+    Config(String),
+    // Calling the LLM provider or another HTTP endpoint failed
+    Transport(String),
+    // The LLM provider answered with a non-success status code
+    Http {
+        status: u16,
+        // Parsed from the Retry-After header (seconds form only)
+        retry_after: Option<Duration>,
+        message: String,
+    },
+    // A model response could not be decoded into the expected structure
+    Decode(String),
+    // A fact sheet or build sheet field the agent depends on has not been filled in
+    MissingInput(String),
+    // Generated code failed to build or run too many times
+    Build(String),
+    // The human operator refused to execute AI written code
+    SafetyAbort(String),
+    // The run exhausted its spend budget
+    Budget(String),
+    // Reading or writing project files failed
+    Io(std::io::Error),
+    // A spawned task or blocking job panicked or was cancelled
+    Task(String),
+}
+
+impl fmt::Display for AgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(msg) => write!(f, "Configuration error: {}", msg),
+            Self::Transport(msg) => write!(f, "Transport error: {}", msg),
+            Self::Http {
+                status, message, ..
+            } => write!(f, "HTTP {} error: {}", status, message),
+            Self::Decode(msg) => write!(f, "Decode error: {}", msg),
+            Self::MissingInput(msg) => write!(f, "Missing input: {}", msg),
+            Self::Build(msg) => write!(f, "Build error: {}", msg),
+            Self::SafetyAbort(msg) => write!(f, "Safety abort: {}", msg),
+            Self::Budget(msg) => write!(f, "Budget exhausted: {}", msg),
+            Self::Io(e) => write!(f, "IO error: {}", e),
+            Self::Task(msg) => write!(f, "Task error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AgentError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AgentError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<reqwest::Error> for AgentError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Transport(e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for AgentError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Task(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AgentError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Decode(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_agent_error_conversions() {
+        let decode_err: AgentError = serde_json::from_str::<Vec<String>>("not json")
+            .unwrap_err()
+            .into();
+        assert!(matches!(decode_err, AgentError::Decode(_)));
+
+        let io_err: AgentError =
+            std::io::Error::new(std::io::ErrorKind::NotFound, "missing main.rs").into();
+        assert!(io_err.to_string().contains("missing main.rs"));
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Message {
     pub role: String,
     pub content: String,
@@ -11,6 +11,16 @@ pub struct ChatCompletion {
     pub model: String,
     pub messages: Vec<Message>,
     pub temperature: f32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+// Asks for token usage in the last streamed chunk, which is otherwise left out
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub struct StreamOptions {
+    pub include_usage: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -23,8 +33,55 @@ pub struct APIChoice {
     pub message: APIMessage,
 }
 
+// Token counts reported by the API for a single completion
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct APIResponse {
     pub choices: Vec<APIChoice>,
+    pub model: Option<String>,
+    pub usage: Option<TokenUsage>,
+}
+
+// One server-sent event of a streamed completion
+#[derive(Debug, Deserialize)]
+pub struct APIStreamChunk {
+    #[serde(default)]
+    pub choices: Vec<APIStreamChoice>,
+    pub model: Option<String>,
+    pub usage: Option<TokenUsage>,
+    pub error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct APIStreamChoice {
+    pub delta: APIDelta,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct APIDelta {
+    pub content: Option<String>,
 }
 
+// What a provider hands back: the completion plus whatever it knows about its cost
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmResponse {
+    pub content: String,
+    pub model: Option<String>,
+    pub usage: Option<TokenUsage>,
+}
+
+impl LlmResponse {
+    // Response with no usage information (i.e. replayed fixtures, test doubles)
+    pub fn text(content: String) -> Self {
+        Self {
+            content,
+            model: None,
+            usage: None,
+        }
+    }
+}
@@ -1 +1,2 @@
-pub mod llm;
\ No newline at end of file
+pub mod errors;
+pub mod llm;
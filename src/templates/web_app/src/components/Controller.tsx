@@ -1,18 +1,15 @@
-import { useState } from "react";
+import { BrowserRouter, Navigate, Route, Routes } from "react-router-dom";
 import Navigation from "./shared/Navigation";
-import PageOne from "./pages/PageOne";
-// import PageTwo from "./pages/PageTwo";
-// import Footer from "./shared/Footer";
+import Footer from "./shared/Footer";
 
 function Controller() {
-  const [currentPage, setCurrentPage] = useState("page1");
-
   return (
-    <div>
-      <Navigation currentPage={currentPage} setCurrentPage={setCurrentPage} />
-      <PageOne />
-      {/* <Footer /> */}
-    </div>
+    <BrowserRouter>
+      <Navigation />
+      <Routes>
+      </Routes>
+      <Footer />
+    </BrowserRouter>
   );
 }
 
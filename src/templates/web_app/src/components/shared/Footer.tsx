@@ -0,0 +1,24 @@
+import { NavLink } from "react-router-dom";
+import { navLinks } from "../../navLinks";
+
+function Footer() {
+  return (
+    <footer className="bg-white shadow-inner">
+      <nav className="container mx-auto flex justify-center px-4 py-2">
+        {navLinks.map(({ label, path }) => (
+          <NavLink
+            key={path}
+            to={path}
+            className={({ isActive }) =>
+              `mx-2 ${isActive ? "text-blue-500" : "text-gray-700"}`
+            }
+          >
+            {label}
+          </NavLink>
+        ))}
+      </nav>
+    </footer>
+  );
+}
+
+export default Footer;
@@ -1,43 +1,30 @@
 import React, { useState } from "react";
+import { NavLink } from "react-router-dom";
+import { navLinks } from "../../navLinks";
 import Logo from "./Logo";
 
-type Props = {
-  currentPage: string;
-  setCurrentPage: any;
-};
-
-const Navigation: React.FC<Props> = ({ currentPage, setCurrentPage }) => {
+const Navigation: React.FC = () => {
   const [isOpen, setIsOpen] = useState(false);
 
-  const pages = [
-    { name: "Home", id: "home_page" },
-    { name: "About", id: "about_page" },
-  ];
-
   const toggleMenu = () => {
     setIsOpen(!isOpen);
   };
 
-  const handleClick = (pageId: string) => {
-    setCurrentPage(pageId);
-    setIsOpen(false);
-  };
-
   return (
     <header className="bg-white shadow-md">
       <div className="container mx-auto flex justify-between items-center px-4 py-2">
         <Logo />
         <div className="hidden sm:flex">
-          {pages.map(({ name, id }) => (
-            <button
-              key={id}
-              className={`ml-4 ${
-                currentPage === id ? "text-blue-500" : "text-gray-700"
-              }`}
-              onClick={() => handleClick(id)}
+          {navLinks.map(({ label, path }) => (
+            <NavLink
+              key={path}
+              to={path}
+              className={({ isActive }) =>
+                `ml-4 ${isActive ? "text-blue-500" : "text-gray-700"}`
+              }
             >
-              {name}
-            </button>
+              {label}
+            </NavLink>
           ))}
         </div>
         <div className="sm:hidden">
@@ -58,16 +45,17 @@ const Navigation: React.FC<Props> = ({ currentPage, setCurrentPage }) => {
       {isOpen && (
         <div className="sm:hidden bg-white shadow-md">
           <nav>
-            {pages.map(({ name, id }) => (
-              <button
-                key={id}
-                className={`block w-full p-4 ${
-                  currentPage === id ? "text-blue-500" : "text-gray-700"
-                }`}
-                onClick={() => handleClick(id)}
+            {navLinks.map(({ label, path }) => (
+              <NavLink
+                key={path}
+                to={path}
+                className={({ isActive }) =>
+                  `block w-full p-4 ${isActive ? "text-blue-500" : "text-gray-700"}`
+                }
+                onClick={() => setIsOpen(false)}
               >
-                {name}
-              </button>
+                {label}
+              </NavLink>
             ))}
           </nav>
         </div>
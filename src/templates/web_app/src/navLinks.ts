@@ -0,0 +1,4 @@
+export type NavLinkItem = { label: string; path: string };
+
+export const navLinks: NavLinkItem[] = [
+];